@@ -26,28 +26,129 @@ pub struct App<'a> {
     pub show_tabs: bool,
     pub tabs: TabsState<'a>,
     pub vertical_scroll_state: ScrollbarState,
+    /// The first wrapped line of the events shown, kept at the bottom while following.
     pub vertical_scroll: usize,
+    /// Whether the events view sticks to the newest event, stopped by scrolling up.
+    pub follow: bool,
+    /// Events shown below the view since following stopped.
+    pub new_below: usize,
+    /// How many lines of events fit on screen, as of the last draw.
+    pub events_height: usize,
+    /// Where the tabs were drawn, `None` while they're hidden.
+    pub tabs_area: Option<Rect>,
+    /// Where the events were drawn, without the border.
+    pub events_area: Rect,
+    /// The sequence number of the event on each visible row of the events view.
+    pub event_rows: Vec<Option<u64>>,
+    pub event_detail: Option<EventDetail>,
+    pub status: ConnectionStatus,
+    pub connect_dialog: Option<ConnectDialog>,
+    pub filter_editor: Option<FilterEditor>,
+    pub search: Option<Search>,
+    /// Which events are shown, all of them when `None`.
+    pub filter: Option<ActiveFilter>,
+    pub scheme: Scheme,
+    pub commands: UnboundedSender<ConnectionCommand>,
+    /// Whether the current server has been connected to since it was picked.
+    pub has_connected: bool,
+    /// Whether the connect dialog has already been opened because the current server
+    /// couldn't be reached, so closing it isn't undone by the next retry.
+    pub prompted: bool,
+    pub config: Config,
+    /// Where the config is saved, `None` if it couldn't be loaded.
+    pub config_path: Option<PathBuf>,
+    pub config_error: Option<String>,
+    pub diagnostics: Diagnostics,
+    /// Recent messages, so deletes can show what was removed.
+    pub messages: MessageCache,
+    /// Names of everything in the guilds we've seen, so IDs can be resolved locally.
+    pub guilds: GuildRegistry,
+    /// Who is in which voice channel, as far as we've seen.
+    pub voice: VoiceOccupancy,
+    pub watchlist: Watchlist,
+    /// Set when the terminal bell should ring, cleared once it has.
+    pub bell: bool,
+    pub events: EventStore,
+    pub wrapped: WrapCache,
+    /// Whether anything changed since the last draw.
+    pub dirty: bool,
 }
 
-use std::sync::Mutex;
+/// The popup showing everything about one event.
+pub struct EventDetail {
+    pub seq: u64,
+    pub scroll: u16,
+}
 
-use lazy_static::lazy_static;
-use ratatui::{text, widgets::ScrollbarState};
+use std::{path::PathBuf, time::Instant};
 
-lazy_static! {
-    pub static ref MESSAGES: Mutex<Vec<Vec<text::Line<'static>>>> = Mutex::new(Vec::new());
-}
+use crossterm::event::{KeyCode, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{layout::Rect, text, widgets::ScrollbarState};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    cache::{GuildRegistry, MessageCache, MESSAGE_CACHE_SIZE},
+    cli::Scheme,
+    config::Config,
+    connection::{ConnectionCommand, ConnectionState, ConnectionStatus, StatusUpdate},
+    diagnostics::Diagnostics,
+    dialog::{self, ConnectDialog, DialogAction, FilterAction, FilterEditor},
+    event_handlers,
+    filter::{ActiveFilter, Filter},
+    search::Search,
+    store::EventStore,
+    voice::VoiceOccupancy,
+    watchlist::{Alert, Watchlist},
+    wrap::WrapCache,
+};
 
 impl<'a> App<'a> {
-    pub fn new(title: &'a str) -> App<'a> {
+    pub fn new(
+        title: &'a str,
+        server: String,
+        scheme: Scheme,
+        commands: UnboundedSender<ConnectionCommand>,
+        mut config: Config,
+        config_path: Option<PathBuf>,
+        events: EventStore,
+    ) -> App<'a> {
+        dialog::remember_server(&mut config.servers, &server);
         App {
             title,
             should_quit: false,
-            logs_border: false,
-            show_tabs: false,
-            tabs: TabsState::new(vec!["Events", "Placeholder", "Placeholder"]),
+            logs_border: config.logs_border,
+            show_tabs: config.show_tabs,
+            tabs: TabsState::new(vec!["Events", "Voice", "Diagnostics", "Alerts"]),
             vertical_scroll_state: ScrollbarState::default(),
             vertical_scroll: 0,
+            follow: true,
+            new_below: 0,
+            events_height: 0,
+            tabs_area: None,
+            events_area: Rect::default(),
+            event_rows: Vec::new(),
+            event_detail: None,
+            status: ConnectionStatus::new(server),
+            connect_dialog: None,
+            filter_editor: None,
+            search: None,
+            filter: None,
+            scheme,
+            commands,
+            has_connected: false,
+            prompted: false,
+            config,
+            config_path,
+            config_error: None,
+            diagnostics: Diagnostics::default(),
+            messages: MessageCache::new(MESSAGE_CACHE_SIZE),
+            guilds: GuildRegistry::default(),
+            voice: VoiceOccupancy::default(),
+            watchlist: Watchlist::default(),
+            bell: false,
+            events,
+            wrapped: WrapCache::default(),
+            dirty: true,
         }
     }
     pub fn on_right(&mut self) {
@@ -59,27 +160,447 @@ impl<'a> App<'a> {
     }
 
     pub fn scroll_up(&mut self) {
-        self.vertical_scroll = self.vertical_scroll.saturating_add(1);
-        self.vertical_scroll_state = self.vertical_scroll_state.position(self.vertical_scroll);
+        self.scroll_up_by(1);
     }
 
     pub fn scroll_down(&mut self) {
-        self.vertical_scroll = self.vertical_scroll.saturating_sub(1);
-        self.vertical_scroll_state = self.vertical_scroll_state.position(self.vertical_scroll);
+        self.scroll_down_by(1);
+    }
+
+    pub fn page_up(&mut self) {
+        self.scroll_up_by(self.events_height.saturating_sub(1).max(1));
+    }
+
+    pub fn page_down(&mut self) {
+        self.scroll_down_by(self.events_height.saturating_sub(1).max(1));
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.follow = false;
+        self.vertical_scroll = 0;
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.follow = true;
+        self.new_below = 0;
+    }
+
+    /// Scrolling up stops following, reaching the bottom again resumes it when drawn.
+    fn scroll_up_by(&mut self, lines: usize) {
+        self.follow = false;
+        self.vertical_scroll = self.vertical_scroll.saturating_sub(lines);
+    }
+
+    fn scroll_down_by(&mut self, lines: usize) {
+        self.vertical_scroll = self.vertical_scroll.saturating_add(lines);
     }
 
     pub fn on_key(&mut self, c: char) {
-        match c {
-            'q' => {
-                self.should_quit = true;
+        let keys = &self.config.keybindings;
+        if c == keys.quit {
+            self.should_quit = true;
+        } else if c == keys.scroll_up {
+            self.scroll_up();
+        } else if c == keys.scroll_down {
+            self.scroll_down();
+        } else if c == keys.toggle_border {
+            self.logs_border = !self.logs_border;
+            self.config.logs_border = self.logs_border;
+            self.save_config();
+        } else if c == keys.toggle_tabs {
+            self.show_tabs = !self.show_tabs;
+            self.config.show_tabs = self.show_tabs;
+            self.save_config();
+        } else if c == keys.cycle_theme {
+            self.config.theme = self.config.theme.next();
+            self.save_config();
+        } else if c == keys.toggle_reaction_collapse {
+            self.config.collapse_reactions = !self.config.collapse_reactions;
+            self.save_config();
+        } else if c == keys.toggle_unknown_expand {
+            self.config.expand_unknown_events = !self.config.expand_unknown_events;
+            self.save_config();
+        } else if c == keys.toggle_mouse {
+            self.config.mouse_capture = !self.config.mouse_capture;
+            self.save_config();
+        } else if c == keys.toggle_bell {
+            self.config.watch_bell = !self.config.watch_bell;
+            self.save_config();
+        } else if c == keys.search {
+            self.open_search();
+        } else if c == keys.search_next {
+            self.next_match(true);
+        } else if c == keys.search_previous {
+            self.next_match(false);
+        } else if c == keys.filter {
+            self.open_filter_editor();
+        } else if c == keys.connect {
+            self.open_connect_dialog();
+        }
+    }
+
+    /// Writes the config back to disk, surfacing any error in the status bar.
+    pub fn save_config(&mut self) {
+        let Some(path) = &self.config_path else {
+            return;
+        };
+        self.config_error = self
+            .config
+            .save(path)
+            .err()
+            .map(|err| format!("{}: {err}", path.display()));
+    }
+
+    pub fn open_connect_dialog(&mut self) {
+        if self.connect_dialog.is_none() {
+            self.connect_dialog = Some(ConnectDialog::new(&self.status.server));
+        }
+    }
+
+    /// Starts a new search from the top of the events on screen.
+    pub fn open_search(&mut self) {
+        self.tabs.index = 0;
+        let anchor = self.vertical_scroll.saturating_sub(self.evicted_rows());
+        self.search = Some(Search::new(self.config.search_mode, anchor));
+        self.wrapped.set_search(None);
+    }
+
+    pub fn close_search(&mut self) {
+        self.search = None;
+        self.wrapped.set_search(None);
+    }
+
+    pub fn on_search_key(&mut self, code: KeyCode) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => {
+                self.close_search();
+                return;
+            }
+            KeyCode::Enter => {
+                search.editing = false;
+                if search.query.is_empty() {
+                    self.close_search();
+                }
+                return;
+            }
+            KeyCode::Tab => {
+                search.mode = search.mode.next();
+                self.config.search_mode = search.mode;
+                self.save_config();
+            }
+            KeyCode::Char(c) => search.query.push(c),
+            KeyCode::Backspace => {
+                search.query.pop();
+            }
+            _ => return,
+        }
+
+        // every change looks for the first match from where the search started again.
+        if let Some(search) = &mut self.search {
+            search.update();
+            search.current = None;
+            self.wrapped.set_search(search.regex());
+        }
+        self.next_match(true);
+    }
+
+    /// Moves to the match after the current one, or before it, wrapping around at the ends
+    /// and scrolling it to the middle of the events view.
+    pub fn next_match(&mut self, forward: bool) {
+        let Some(search) = &self.search else {
+            return;
+        };
+
+        let current = search
+            .current
+            .and_then(|(seq, nth)| self.wrapped.line_index(seq, nth));
+        let matches: Vec<usize> = self.wrapped.matching_lines().collect();
+        let target = if forward {
+            matches
+                .iter()
+                .find(|&&index| current.map_or(index >= search.anchor, |current| index > current))
+                .or(matches.first())
+        } else {
+            matches
+                .iter()
+                .rev()
+                .find(|&&index| current.map_or(index < search.anchor, |current| index < current))
+                .or(matches.last())
+        };
+
+        let Some(&target) = target else {
+            if let Some(search) = &mut self.search {
+                search.current = None;
+            }
+            return;
+        };
+        let position = self.wrapped.line_at(target);
+        if let Some(search) = &mut self.search {
+            search.current = position;
+        }
+        self.tabs.index = 0;
+        self.follow = false;
+        self.vertical_scroll =
+            (target + self.evicted_rows()).saturating_sub(self.events_height / 2);
+    }
+
+    /// The rows above the events taken by the dropped events notice.
+    fn evicted_rows(&self) -> usize {
+        usize::from(self.events.evicted > 0)
+    }
+
+    pub fn open_filter_editor(&mut self) {
+        if self.filter_editor.is_none() {
+            let editor = match &self.filter {
+                Some(filter) => FilterEditor::new(&filter.name, &filter.rules),
+                None => FilterEditor::new("", &[]),
+            };
+            self.filter_editor = Some(editor);
+        }
+    }
+
+    pub fn on_filter_key(&mut self, code: KeyCode) {
+        let Some(editor) = &mut self.filter_editor else {
+            return;
+        };
+
+        match editor.on_key(code, &self.config.filters) {
+            FilterAction::None => {}
+            FilterAction::Close => self.filter_editor = None,
+            FilterAction::Delete(index) => {
+                let removed = self.config.filters.remove(index);
+                if self.config.active_filter.as_ref() == Some(&removed.name) {
+                    self.config.active_filter = None;
+                }
+                // the filter being applied goes with it, or the view stays filtered by it.
+                if self
+                    .filter
+                    .as_ref()
+                    .is_some_and(|filter| filter.name == removed.name)
+                {
+                    self.set_filter(None);
+                }
+                self.save_config();
+            }
+            FilterAction::Apply { name, rules } => {
+                // an empty filter shows everything, there's nothing worth keeping active.
+                let filter = if rules.is_empty() {
+                    None
+                } else {
+                    match ActiveFilter::new(name.clone(), &rules) {
+                        Ok(filter) => Some(filter),
+                        Err(err) => {
+                            editor.error = Some(err.to_string());
+                            return;
+                        }
+                    }
+                };
+
+                if !name.is_empty() {
+                    let saved = Filter {
+                        name: name.clone(),
+                        rules,
+                    };
+                    match self.config.filters.iter_mut().find(|f| f.name == name) {
+                        Some(existing) => *existing = saved,
+                        None => self.config.filters.push(saved),
+                    }
+                }
+                self.config.active_filter = filter
+                    .as_ref()
+                    .filter(|filter| !filter.name.is_empty())
+                    .map(|filter| filter.name.clone());
+                self.save_config();
+                self.set_filter(filter);
+                self.filter_editor = None;
+            }
+        }
+    }
+
+    /// Compiles the watchlist from the config, surfacing a bad regex in the status bar.
+    pub fn load_watchlist(&mut self) {
+        match Watchlist::new(&self.config.watchlist) {
+            Ok(watchlist) => self.watchlist = watchlist,
+            Err(err) => self.config_error = Some(format!("watchlist: {err}")),
+        }
+    }
+
+    /// Applies the saved filter named in the config, if there is one.
+    pub fn load_active_filter(&mut self) {
+        let Some(name) = self.config.active_filter.clone() else {
+            return;
+        };
+        let Some(saved) = self.config.filters.iter().find(|f| f.name == name) else {
+            self.config_error = Some(format!("the active filter `{name}` isn't saved"));
+            return;
+        };
+        match ActiveFilter::new(name.clone(), &saved.rules) {
+            Ok(filter) => self.set_filter(Some(filter)),
+            Err(err) => self.config_error = Some(format!("filter `{name}`: {err}")),
+        }
+    }
+
+    /// Swaps the filter, the events are wrapped again and the view jumps to the newest.
+    pub fn set_filter(&mut self, filter: Option<ActiveFilter>) {
+        self.filter = filter;
+        self.wrapped.invalidate();
+        self.follow = true;
+        self.new_below = 0;
+    }
+
+    pub fn on_detail_key(&mut self, code: KeyCode) {
+        let Some(detail) = &mut self.event_detail else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc | KeyCode::Enter => self.event_detail = None,
+            KeyCode::Char(c) if c == self.config.keybindings.quit => self.event_detail = None,
+            KeyCode::Up => detail.scroll = detail.scroll.saturating_sub(1),
+            KeyCode::Down => detail.scroll = detail.scroll.saturating_add(1),
+            KeyCode::PageUp => detail.scroll = detail.scroll.saturating_sub(10),
+            KeyCode::PageDown => detail.scroll = detail.scroll.saturating_add(10),
+            KeyCode::Home => detail.scroll = 0,
+            _ => {}
+        }
+    }
+
+    pub fn on_mouse(&mut self, event: MouseEvent) {
+        if self.connect_dialog.is_some()
+            || self.filter_editor.is_some()
+            || self.search.as_ref().is_some_and(|search| search.editing)
+        {
+            return;
+        }
+
+        if let Some(detail) = &mut self.event_detail {
+            match event.kind {
+                MouseEventKind::ScrollUp => detail.scroll = detail.scroll.saturating_sub(3),
+                MouseEventKind::ScrollDown => detail.scroll = detail.scroll.saturating_add(3),
+                MouseEventKind::Down(MouseButton::Right) => self.event_detail = None,
+                _ => {}
+            }
+            return;
+        }
+
+        match event.kind {
+            MouseEventKind::ScrollUp => self.scroll_up_by(3),
+            MouseEventKind::ScrollDown => self.scroll_down_by(3),
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(index) = self.tab_at(event.column, event.row) {
+                    self.tabs.index = index;
+                } else if self.tabs.index == 0
+                    && contains(self.events_area, event.column, event.row)
+                {
+                    let row = (event.row - self.events_area.y) as usize;
+                    if let Some(Some(seq)) = self.event_rows.get(row) {
+                        self.event_detail = Some(EventDetail {
+                            seq: *seq,
+                            scroll: 0,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The tab under the cursor, going by how the `Tabs` widget lays out its titles:
+    /// ` title ` followed by a one column divider.
+    fn tab_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.tabs_area?;
+        // the titles are on the first row inside the border.
+        if row != area.y + 1 || column <= area.x {
+            return None;
+        }
+
+        let mut x = area.x + 1;
+        for (index, title) in self.tabs.titles.iter().enumerate() {
+            let end = x + text::Line::from(*title).width() as u16 + 2;
+            if column < end {
+                return Some(index);
+            }
+            x = end + 1;
+        }
+        None
+    }
+
+    pub fn on_dialog_key(&mut self, code: KeyCode) {
+        let Some(connect_dialog) = &mut self.connect_dialog else {
+            return;
+        };
+
+        match connect_dialog.on_key(code, &self.config.servers, self.scheme) {
+            DialogAction::None => {}
+            DialogAction::Close => self.connect_dialog = None,
+            DialogAction::Connect(url) => self.connect(url),
+        }
+    }
+
+    /// Switches to `url`, or retries it if it's the current server.
+    pub fn connect(&mut self, url: String) {
+        if let Err(err) = self.commands.send(ConnectionCommand::Connect(url.clone())) {
+            if let Some(connect_dialog) = &mut self.connect_dialog {
+                connect_dialog.error = Some(format!("connection task has stopped: {err}"));
+            }
+            return;
+        }
+
+        dialog::remember_server(&mut self.config.servers, &url);
+        self.config.default_server = Some(url.clone());
+        self.save_config();
+        self.status.server = url;
+        self.has_connected = false;
+        self.prompted = false;
+        self.connect_dialog = None;
+    }
+
+    pub fn on_status(&mut self, update: StatusUpdate) {
+        if let StatusUpdate::State(state) = &update {
+            match state {
+                ConnectionState::Connected => self.has_connected = true,
+                // the first attempt at this server failed, or we ran out of retries.
+                ConnectionState::Retrying { .. } if !self.has_connected && !self.prompted => {
+                    self.prompted = true;
+                    self.open_connect_dialog()
+                }
+                ConnectionState::GaveUp { .. } => self.open_connect_dialog(),
+                _ => {}
+            }
+        }
+        self.status.apply(&update);
+        match update {
+            StatusUpdate::Unparsed(failure) => self.diagnostics.record(failure),
+            StatusUpdate::Event(mut event) => {
+                let rules = self.watchlist.check(&event);
+                if !rules.is_empty() {
+                    event.watched = true;
+                    // an event that renders to nothing would be an empty alert.
+                    let lines = event_handlers::render_event(&event, &self.config);
+                    if !lines.is_empty() {
+                        self.watchlist.record(Alert {
+                            received: Instant::now(),
+                            rules,
+                            lines,
+                        });
+                        self.bell |= self.config.watch_bell;
+                    }
+                }
+
+                // counted towards `new_below` once it's wrapped, if it shows up at all.
+                self.events.push(event);
             }
-            'j' => self.scroll_up(),
-            'k' => self.scroll_down(),
-            'e' => self.logs_border = !self.logs_border,
-            'r' => self.show_tabs = !self.show_tabs,
             _ => {}
         }
     }
 
     pub fn on_tick(&mut self) {}
 }
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    column >= area.x && column < area.right() && row >= area.y && row < area.bottom()
+}
@@ -0,0 +1,193 @@
+use std::collections::{HashMap, VecDeque};
+
+use serenity::all::{ChannelId, EmojiId, Guild, GuildChannel, GuildId, Message, MessageId, RoleId};
+
+/// How many messages are remembered so deletes can show what was removed.
+pub const MESSAGE_CACHE_SIZE: usize = 2000;
+
+/// The most recent messages we've seen, evicting the oldest first.
+pub struct MessageCache {
+    capacity: usize,
+    messages: HashMap<MessageId, Message>,
+    order: VecDeque<MessageId>,
+}
+
+impl MessageCache {
+    pub fn new(capacity: usize) -> MessageCache {
+        MessageCache {
+            capacity,
+            messages: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Inserts or replaces a message, an edited message keeps its original position.
+    pub fn insert(&mut self, message: Message) {
+        let id = message.id;
+        if self.messages.insert(id, message).is_some() {
+            return;
+        }
+
+        self.order.push_back(id);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.messages.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: MessageId) -> Option<Message> {
+        let message = self.messages.remove(&id)?;
+        self.order.retain(|cached| *cached != id);
+        Some(message)
+    }
+}
+
+/// Names of everything in the guilds we've seen, so handlers can resolve IDs locally.
+#[derive(Debug, Default)]
+pub struct GuildRegistry {
+    pub guilds: HashMap<GuildId, GuildInfo>,
+}
+
+#[derive(Debug, Default)]
+pub struct GuildInfo {
+    pub name: String,
+    pub channels: HashMap<ChannelId, String>,
+    pub roles: HashMap<RoleId, String>,
+    pub emojis: HashMap<EmojiId, String>,
+}
+
+impl GuildRegistry {
+    pub fn insert(&mut self, guild: &Guild) {
+        let info = GuildInfo {
+            name: guild.name.clone(),
+            channels: guild
+                .channels
+                .values()
+                .chain(&guild.threads)
+                .map(|channel| (channel.id, channel.name.clone()))
+                .collect(),
+            roles: guild
+                .roles
+                .values()
+                .map(|role| (role.id, role.name.clone()))
+                .collect(),
+            emojis: guild
+                .emojis
+                .values()
+                .map(|emoji| (emoji.id, emoji.name.clone()))
+                .collect(),
+        };
+        self.guilds.insert(guild.id, info);
+    }
+
+    pub fn upsert_channel(&mut self, channel: &GuildChannel) {
+        if let Some(guild) = self.guilds.get_mut(&channel.guild_id) {
+            guild.channels.insert(channel.id, channel.name.clone());
+        }
+    }
+
+    pub fn remove_channel(&mut self, guild_id: GuildId, channel_id: ChannelId) {
+        if let Some(guild) = self.guilds.get_mut(&guild_id) {
+            guild.channels.remove(&channel_id);
+        }
+    }
+
+    pub fn guild_name(&self, guild_id: GuildId) -> Option<&str> {
+        self.guilds.get(&guild_id).map(|guild| guild.name.as_str())
+    }
+
+    pub fn channel_name(&self, guild_id: GuildId, channel_id: ChannelId) -> Option<&str> {
+        self.guilds
+            .get(&guild_id)?
+            .channels
+            .get(&channel_id)
+            .map(String::as_str)
+    }
+
+    pub fn role_name(&self, guild_id: GuildId, role_id: RoleId) -> Option<&str> {
+        self.guilds
+            .get(&guild_id)?
+            .roles
+            .get(&role_id)
+            .map(String::as_str)
+    }
+
+    /// Emojis are looked up in every guild, reactions can use emojis from anywhere.
+    pub fn emoji_name(&self, emoji_id: EmojiId) -> Option<&str> {
+        self.guilds
+            .values()
+            .find_map(|guild| guild.emojis.get(&emoji_id))
+            .map(String::as_str)
+    }
+}
+
+/// The names an event refers to, looked up in the [`GuildRegistry`] when it arrived so
+/// later changes to the registry don't change how it's shown.
+#[derive(Debug, Default)]
+pub struct Names {
+    pub guild: Option<String>,
+    pub channels: HashMap<ChannelId, String>,
+    pub roles: HashMap<RoleId, String>,
+    pub emojis: HashMap<EmojiId, String>,
+}
+
+impl Names {
+    pub fn channel(&self, channel_id: ChannelId) -> Option<&str> {
+        self.channels.get(&channel_id).map(String::as_str)
+    }
+
+    pub fn role(&self, role_id: RoleId) -> Option<&str> {
+        self.roles.get(&role_id).map(String::as_str)
+    }
+
+    pub fn emoji(&self, emoji_id: EmojiId) -> Option<&str> {
+        self.emojis.get(&emoji_id).map(String::as_str)
+    }
+}
+
+/// Collects [`Names`] for the IDs of one guild that the registry knows about.
+pub struct NameResolver<'a> {
+    registry: &'a GuildRegistry,
+    guild_id: Option<GuildId>,
+    pub names: Names,
+}
+
+impl<'a> NameResolver<'a> {
+    pub fn new(registry: &'a GuildRegistry, guild_id: Option<GuildId>) -> NameResolver<'a> {
+        NameResolver {
+            registry,
+            guild_id,
+            names: Names {
+                guild: guild_id
+                    .and_then(|guild_id| registry.guild_name(guild_id))
+                    .map(String::from),
+                ..Names::default()
+            },
+        }
+    }
+
+    pub fn channel(&mut self, channel_id: ChannelId) {
+        let Some(guild_id) = self.guild_id else {
+            return;
+        };
+        if let Some(name) = self.registry.channel_name(guild_id, channel_id) {
+            self.names.channels.insert(channel_id, name.to_string());
+        }
+    }
+
+    pub fn role(&mut self, role_id: RoleId) {
+        let Some(guild_id) = self.guild_id else {
+            return;
+        };
+        if let Some(name) = self.registry.role_name(guild_id, role_id) {
+            self.names.roles.insert(role_id, name.to_string());
+        }
+    }
+
+    pub fn emoji(&mut self, emoji_id: EmojiId) {
+        if let Some(name) = self.registry.emoji_name(emoji_id) {
+            self.names.emojis.insert(emoji_id, name.to_string());
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::{fmt, path::PathBuf, str::FromStr, time::Duration};
+
+use argh::FromArgs;
+use url::{Host, Url};
+
+/// A client for jamespy, so you don't have to look at the bot window.
+#[derive(FromArgs, Debug)]
+pub struct Args {
+    /// address of the jamespy websocket server, defaults to the server from the config.
+    #[argh(option, short = 'a', from_str_fn(parse_address))]
+    pub address: Option<String>,
+
+    /// port the jamespy websocket server is listening on, defaults to the config server's
+    /// or 8080.
+    #[argh(option, short = 'p')]
+    pub port: Option<u16>,
+
+    /// websocket scheme to connect with, either ws or wss, defaults to the config server's
+    /// or ws.
+    #[argh(option)]
+    pub scheme: Option<Scheme>,
+
+    /// how many reconnect attempts in a row before giving up, 0 retries forever.
+    #[argh(option, default = "10")]
+    pub max_retries: u32,
+
+    /// how often the interface is redrawn, in milliseconds.
+    #[argh(option, short = 't', default = "50")]
+    pub tick_rate: u64,
+
+    /// how many events are kept in the history, overrides the config.
+    #[argh(option)]
+    pub history_size: Option<usize>,
+
+    /// roughly how much memory the history can use in MiB, overrides the config.
+    #[argh(option)]
+    pub history_memory: Option<usize>,
+
+    /// path to the config file, defaults to the XDG config directory.
+    #[argh(option, short = 'c')]
+    pub config: Option<PathBuf>,
+}
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1";
+pub const DEFAULT_PORT: u16 = 8080;
+
+fn parse_address(value: &str) -> Result<String, String> {
+    if value.contains("://") {
+        return Err(format!(
+            "`{value}` should be just the host, pass the scheme with --scheme"
+        ));
+    }
+    Host::parse(value).map_err(|_| {
+        format!("`{value}` should be a host name or IP address, pass the port with --port")
+    })?;
+    Ok(value.to_string())
+}
+
+impl Args {
+    /// The websocket url to connect to: `server` from the config with the scheme, address
+    /// and port given on the command line swapped in, or the defaults without a server.
+    pub fn url(&self, server: Option<&str>) -> String {
+        let Some(server) = server.and_then(|server| Url::parse(server).ok()) else {
+            return format!(
+                "{}://{}:{}",
+                self.scheme(),
+                self.address.as_deref().unwrap_or(DEFAULT_ADDRESS),
+                self.port.unwrap_or(DEFAULT_PORT)
+            );
+        };
+
+        let scheme = self
+            .scheme
+            .map_or_else(|| server.scheme().to_string(), |scheme| scheme.to_string());
+        let host = self
+            .address
+            .as_deref()
+            .or(server.host_str())
+            .unwrap_or(DEFAULT_ADDRESS);
+        let port = self
+            .port
+            .or(server.port())
+            .map_or(String::new(), |port| format!(":{port}"));
+        let path = match server.path() {
+            "/" => "",
+            path => path,
+        };
+        format!("{scheme}://{host}{port}{path}")
+    }
+
+    pub fn scheme(&self) -> Scheme {
+        self.scheme.unwrap_or(Scheme::Ws)
+    }
+
+    /// At least a millisecond, the tick interval can't be zero.
+    pub fn tick_rate(&self) -> Duration {
+        Duration::from_millis(self.tick_rate.max(1))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    Ws,
+    Wss,
+}
+
+impl FromStr for Scheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ws" => Ok(Scheme::Ws),
+            "wss" => Ok(Scheme::Wss),
+            _ => Err(format!("unknown scheme `{s}`, expected `ws` or `wss`")),
+        }
+    }
+}
+
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scheme::Ws => write!(f, "ws"),
+            Scheme::Wss => write!(f, "wss"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(address: Option<&str>, port: Option<u16>, scheme: Option<Scheme>) -> Args {
+        Args {
+            address: address.map(String::from),
+            port,
+            scheme,
+            max_retries: 10,
+            tick_rate: 50,
+            history_size: None,
+            history_memory: None,
+            config: None,
+        }
+    }
+
+    #[test]
+    fn defaults_without_a_server() {
+        assert_eq!(args(None, None, None).url(None), "ws://127.0.0.1:8080");
+        assert_eq!(
+            args(Some("example.com"), None, Some(Scheme::Wss)).url(None),
+            "wss://example.com:8080"
+        );
+    }
+
+    #[test]
+    fn overrides_apply_to_the_config_server() {
+        let server = Some("ws://192.168.0.113:9000");
+        assert_eq!(
+            args(None, None, None).url(server),
+            "ws://192.168.0.113:9000"
+        );
+        assert_eq!(
+            args(None, None, Some(Scheme::Wss)).url(server),
+            "wss://192.168.0.113:9000"
+        );
+        assert_eq!(
+            args(None, Some(8080), None).url(server),
+            "ws://192.168.0.113:8080"
+        );
+        assert_eq!(
+            args(Some("localhost"), None, None).url(server),
+            "ws://localhost:9000"
+        );
+    }
+
+    #[test]
+    fn address_is_only_a_host() {
+        assert!(parse_address("192.168.0.113").is_ok());
+        assert!(parse_address("ws://host").is_err());
+        assert!(parse_address("host:8080").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{filter::Filter, search::SearchMode, theme::ThemeName, watchlist::Watch};
+
+/// Settings persisted between runs, stored as TOML in the XDG config dir.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// Known servers, most recently used first.
+    pub servers: Vec<String>,
+    /// The server last picked in the connect dialog, used on startup when no
+    /// address is given on the command line.
+    pub default_server: Option<String>,
+    /// How many events are kept before the oldest are dropped.
+    pub history_limit: usize,
+    /// Roughly how much memory the history can use in MiB, unlimited if unset.
+    pub history_memory_mib: Option<usize>,
+    pub theme: ThemeName,
+    pub keybindings: Keybindings,
+    pub logs_border: bool,
+    pub show_tabs: bool,
+    /// Collapse reactions on the same message in quick succession into one line.
+    pub collapse_reactions: bool,
+    /// Show events without a dedicated handler as pretty-printed JSON instead of one line.
+    pub expand_unknown_events: bool,
+    /// Capture the mouse for scrolling and clicking, release it to select text natively.
+    pub mouse_capture: bool,
+    /// Saved event filters, picked from the filter editor.
+    pub filters: Vec<Filter>,
+    /// The saved filter applied on startup.
+    pub active_filter: Option<String>,
+    /// How the last search matched, new searches start with it.
+    pub search_mode: SearchMode,
+    /// Words, regexes, users and channels to alert on in new and edited messages.
+    pub watchlist: Vec<Watch>,
+    /// Ring the terminal bell when a message matches the watchlist.
+    pub watch_bell: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            servers: Vec::new(),
+            default_server: None,
+            history_limit: 500,
+            history_memory_mib: None,
+            theme: ThemeName::default(),
+            keybindings: Keybindings::default(),
+            logs_border: false,
+            show_tabs: false,
+            collapse_reactions: true,
+            expand_unknown_events: false,
+            mouse_capture: true,
+            filters: Vec::new(),
+            active_filter: None,
+            search_mode: SearchMode::default(),
+            watchlist: Vec::new(),
+            watch_bell: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Keybindings {
+    pub quit: char,
+    pub scroll_up: char,
+    pub scroll_down: char,
+    pub toggle_border: char,
+    pub toggle_tabs: char,
+    pub cycle_theme: char,
+    pub toggle_reaction_collapse: char,
+    pub toggle_unknown_expand: char,
+    pub toggle_mouse: char,
+    pub toggle_bell: char,
+    pub filter: char,
+    pub search: char,
+    pub search_next: char,
+    pub search_previous: char,
+    pub connect: char,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Keybindings {
+            quit: 'q',
+            // vim style, `j` moves down towards newer events.
+            scroll_up: 'k',
+            scroll_down: 'j',
+            toggle_border: 'e',
+            toggle_tabs: 'r',
+            cycle_theme: 't',
+            toggle_reaction_collapse: 'b',
+            toggle_unknown_expand: 'x',
+            toggle_mouse: 'm',
+            toggle_bell: 'g',
+            filter: 'f',
+            search: '/',
+            search_next: 'n',
+            search_previous: 'N',
+            connect: 'c',
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{err}"),
+            ConfigError::Parse(err) => write!(f, "invalid config: {}", err.message()),
+            ConfigError::Serialize(err) => write!(f, "couldn't serialize config: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/jamespy-client/config.toml`, or the platform equivalent.
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("jamespy-client")
+            .join("config.toml")
+    }
+
+    /// Loads the config at `path`, falling back to the defaults if it doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).map_err(ConfigError::Parse),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(ConfigError::Io(err)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let contents = toml::to_string_pretty(self).map_err(ConfigError::Serialize)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(ConfigError::Io)?;
+        }
+        fs::write(path, contents).map_err(ConfigError::Io)
+    }
+
+    /// The server to connect to on startup when none is given on the command line.
+    pub fn startup_server(&self) -> Option<&str> {
+        self.default_server
+            .as_deref()
+            .or_else(|| self.servers.first().map(String::as_str))
+    }
+}
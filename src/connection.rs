@@ -0,0 +1,349 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use futures_util::{SinkExt, StreamExt};
+use rand::Rng;
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    time::{self, MissedTickBehavior},
+};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{
+        protocol::{frame::coding::CloseCode, CloseFrame},
+        Message,
+    },
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::{diagnostics::ParseFailure, store::StoredEvent};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// The window events/sec is averaged over.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// The state of the websocket connection, reported to the `App` by the supervisor.
+#[derive(Debug, Clone)]
+pub enum ConnectionState {
+    Connecting {
+        attempt: u32,
+    },
+    Connected,
+    Retrying {
+        attempt: u32,
+        retry_at: Instant,
+        reason: String,
+    },
+    GaveUp {
+        reason: String,
+    },
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionState::Connecting { attempt: 0 } => write!(f, "connecting"),
+            ConnectionState::Connecting { attempt } => write!(f, "connecting (attempt {attempt})"),
+            ConnectionState::Connected => write!(f, "connected"),
+            ConnectionState::Retrying {
+                retry_at, reason, ..
+            } => {
+                let remaining = retry_at.saturating_duration_since(Instant::now());
+                write!(f, "retrying in {}s ({reason})", remaining.as_secs() + 1)
+            }
+            ConnectionState::GaveUp { reason } => write!(f, "gave up ({reason})"),
+        }
+    }
+}
+
+/// Updates flowing from the websocket and message processing tasks into the `App`.
+#[derive(Debug)]
+pub enum StatusUpdate {
+    State(ConnectionState),
+    /// A frame was turned into an event for the store.
+    Event(StoredEvent),
+    /// A text frame failed to deserialize into an event.
+    Unparsed(ParseFailure),
+    /// A frame was received that can't carry an event, or couldn't be forwarded.
+    Dropped,
+}
+
+/// Everything the status bar shows about the connection.
+pub struct ConnectionStatus {
+    pub server: String,
+    pub state: ConnectionState,
+    pub connected_at: Option<Instant>,
+    pub last_event: Option<Instant>,
+    pub events: u64,
+    pub unparsed: u64,
+    pub dropped: u64,
+    recent: VecDeque<Instant>,
+}
+
+impl ConnectionStatus {
+    pub fn new(server: String) -> ConnectionStatus {
+        ConnectionStatus {
+            server,
+            state: ConnectionState::Connecting { attempt: 0 },
+            connected_at: None,
+            last_event: None,
+            events: 0,
+            unparsed: 0,
+            dropped: 0,
+            recent: VecDeque::new(),
+        }
+    }
+
+    pub fn apply(&mut self, update: &StatusUpdate) {
+        let now = Instant::now();
+        match update {
+            StatusUpdate::State(state) => {
+                self.connected_at = match state {
+                    ConnectionState::Connected => Some(now),
+                    _ => None,
+                };
+                self.state = state.clone();
+            }
+            StatusUpdate::Event(_) => {
+                self.events += 1;
+                self.last_event = Some(now);
+                self.recent.push_back(now);
+            }
+            StatusUpdate::Unparsed(_) => self.unparsed += 1,
+            StatusUpdate::Dropped => self.dropped += 1,
+        }
+        self.expire(now);
+    }
+
+    pub fn uptime(&self) -> Option<Duration> {
+        self.connected_at.map(|at| at.elapsed())
+    }
+
+    pub fn events_per_sec(&mut self) -> f64 {
+        self.expire(Instant::now());
+        self.recent.len() as f64 / RATE_WINDOW.as_secs_f64()
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while let Some(at) = self.recent.front() {
+            if now.duration_since(*at) <= RATE_WINDOW {
+                break;
+            }
+            self.recent.pop_front();
+        }
+    }
+}
+
+/// Commands sent from the `App` to the supervisor.
+#[derive(Debug, Clone)]
+pub enum ConnectionCommand {
+    /// Drop the current connection (if any) and connect to this url, resetting the backoff.
+    Connect(String),
+    /// Close the connection with a close frame and stop.
+    Shutdown,
+}
+
+/// How long to wait for the server to acknowledge our close frame.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long connecting can take before it counts as a failed attempt, an address that
+/// drops packets would otherwise wait for the OS to give up.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often the server is pinged, so a quiet connection still hears back from it.
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+/// How long without any frame, pongs included, before the connection counts as lost.
+const SILENCE_LIMIT: Duration = Duration::from_secs(40);
+
+/// Why an established connection ended.
+enum Ended {
+    Lost(String),
+    /// We were told to connect somewhere else.
+    Switch(String),
+    Shutdown,
+}
+
+/// Keeps a connection to `url` alive, forwarding every frame to `sender`.
+///
+/// When the connection closes or errors the supervisor waits with jittered
+/// exponential backoff and reconnects, giving up after `max_retries` failed
+/// attempts in a row. A `max_retries` of 0 retries forever. At any point a
+/// [`ConnectionCommand`] can switch servers, retry after giving up, or shut the
+/// connection down, which also happens once the `App` is gone.
+pub async fn supervise(
+    mut url: String,
+    max_retries: u32,
+    sender: UnboundedSender<String>,
+    states: UnboundedSender<StatusUpdate>,
+    mut commands: UnboundedReceiver<ConnectionCommand>,
+) {
+    let mut attempt = 0;
+    loop {
+        let _ = states.send(StatusUpdate::State(ConnectionState::Connecting { attempt }));
+
+        let result = tokio::select! {
+            result = time::timeout(CONNECT_TIMEOUT, connect_async(url.as_str())) => result,
+            command = commands.recv() => match command {
+                Some(ConnectionCommand::Connect(new_url)) => {
+                    url = new_url;
+                    attempt = 0;
+                    continue;
+                }
+                Some(ConnectionCommand::Shutdown) | None => return,
+            }
+        };
+
+        let reason = match result {
+            Ok(Ok((ws_stream, _))) => {
+                let _ = states.send(StatusUpdate::State(ConnectionState::Connected));
+                attempt = 1;
+                match handle_websocket_events(ws_stream, &sender, &states, &mut commands).await {
+                    Ended::Lost(reason) => reason,
+                    Ended::Switch(new_url) => {
+                        url = new_url;
+                        attempt = 0;
+                        continue;
+                    }
+                    Ended::Shutdown => return,
+                }
+            }
+            Ok(Err(err)) => {
+                attempt += 1;
+                err.to_string()
+            }
+            Err(_) => {
+                attempt += 1;
+                format!("no answer after {}s", CONNECT_TIMEOUT.as_secs())
+            }
+        };
+
+        if max_retries != 0 && attempt > max_retries {
+            let _ = states.send(StatusUpdate::State(ConnectionState::GaveUp { reason }));
+            // wait until we're told to try again.
+            match commands.recv().await {
+                Some(ConnectionCommand::Connect(new_url)) => {
+                    url = new_url;
+                    attempt = 0;
+                    continue;
+                }
+                Some(ConnectionCommand::Shutdown) | None => return,
+            }
+        }
+
+        let delay = backoff(attempt);
+        let _ = states.send(StatusUpdate::State(ConnectionState::Retrying {
+            attempt,
+            retry_at: Instant::now() + delay,
+            reason,
+        }));
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            command = commands.recv() => match command {
+                Some(ConnectionCommand::Connect(new_url)) => {
+                    url = new_url;
+                    attempt = 0;
+                }
+                Some(ConnectionCommand::Shutdown) | None => return,
+            }
+        }
+    }
+}
+
+/// A random delay between the initial backoff and the capped exponential backoff.
+fn backoff(attempt: u32) -> Duration {
+    let ceiling = INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_BACKOFF);
+    rand::thread_rng().gen_range(INITIAL_BACKOFF.min(ceiling)..=ceiling)
+}
+
+/// Forwards frames until the stream ends or a command ends it, returning why it ended.
+///
+/// The server is pinged every [`PING_INTERVAL`], a connection that has been silent for
+/// [`SILENCE_LIMIT`] is lost even if the socket never errors, like after a NAT drops it.
+async fn handle_websocket_events(
+    mut ws_stream: WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>,
+    ws_sender: &UnboundedSender<String>,
+    states: &UnboundedSender<StatusUpdate>,
+    commands: &mut UnboundedReceiver<ConnectionCommand>,
+) -> Ended {
+    let mut pings = time::interval_at(time::Instant::now() + PING_INTERVAL, PING_INTERVAL);
+    pings.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut last_received = Instant::now();
+    loop {
+        let message = tokio::select! {
+            message = ws_stream.next() => message,
+            _ = pings.tick() => {
+                if last_received.elapsed() >= SILENCE_LIMIT {
+                    return Ended::Lost(format!(
+                        "no response from the server for {}s",
+                        last_received.elapsed().as_secs()
+                    ));
+                }
+                if let Err(err) = ws_stream.send(Message::Ping(Vec::new())).await {
+                    return Ended::Lost(err.to_string());
+                }
+                continue;
+            }
+            command = commands.recv() => {
+                let (ended, code, reason) = match command {
+                    Some(ConnectionCommand::Connect(new_url)) => {
+                        (Ended::Switch(new_url), CloseCode::Normal, "switching servers")
+                    }
+                    Some(ConnectionCommand::Shutdown) | None => {
+                        (Ended::Shutdown, CloseCode::Away, "client shutting down")
+                    }
+                };
+                close(&mut ws_stream, code, reason).await;
+                return ended;
+            }
+        };
+
+        let Some(message) = message else {
+            return Ended::Lost(String::from("connection lost"));
+        };
+        last_received = Instant::now();
+        match message {
+            Ok(Message::Close(frame)) => {
+                return Ended::Lost(match frame {
+                    Some(frame) if !frame.reason.is_empty() => {
+                        format!("closed by server: {}", frame.reason)
+                    }
+                    _ => String::from("closed by server"),
+                });
+            }
+            Ok(Message::Text(text)) => {
+                if ws_sender.send(text).is_err() {
+                    let _ = states.send(StatusUpdate::Dropped);
+                }
+            }
+            Ok(Message::Binary(_)) => {
+                let _ = states.send(StatusUpdate::Dropped);
+            }
+            // pings are answered by tungstenite itself, pongs only count as hearing back.
+            Ok(_) => {}
+            Err(err) => return Ended::Lost(err.to_string()),
+        }
+    }
+}
+
+/// Sends a close frame and waits a little for the server to close its side.
+async fn close(
+    ws_stream: &mut WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>,
+    code: CloseCode,
+    reason: &'static str,
+) {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    if ws_stream.close(Some(frame)).await.is_err() {
+        return;
+    }
+    let _ = tokio::time::timeout(CLOSE_TIMEOUT, async {
+        while let Some(Ok(_)) = ws_stream.next().await {}
+    })
+    .await;
+}
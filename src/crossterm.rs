@@ -1,82 +1,196 @@
 use std::{
     error::Error,
-    io,
-    sync::mpsc,
+    io, panic,
+    path::PathBuf,
+    sync::Arc,
     thread,
     time::{Duration, Instant},
 };
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    cursor::Show,
+    event::{
+        DisableMouseCapture, EnableMouseCapture, Event, EventStream, KeyCode, KeyEventKind,
+        KeyModifiers,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use futures_util::StreamExt;
 use ratatui::prelude::*;
-use tokio::runtime;
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    time::{self, MissedTickBehavior},
+};
 
 use crate::{
-    app::{App, MESSAGES},
-    event::WebSocketEvent,
-    event_handlers, ui,
+    app::App,
+    cache::Names,
+    cli::Args,
+    config::Config,
+    connection::{ConnectionCommand, StatusUpdate},
+    diagnostics::{self, FailureKind},
+    event_handlers,
+    store::{EventStore, Payload, StoredEvent},
+    ui,
 };
-use std::sync::mpsc::Receiver;
 
-pub fn run(tick_rate: Duration, receiver: Receiver<String>) -> Result<(), Box<dyn Error>> {
-    enable_raw_mode()?;
+/// How often the screen is redrawn when nothing has changed.
+const REDRAW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the interface starts with, worked out in `main` from the arguments and config.
+pub struct Startup {
+    pub url: String,
+    pub config: Config,
+    /// Where the config is saved, `None` if it couldn't be read so it isn't clobbered.
+    pub config_path: Option<PathBuf>,
+    pub config_error: Option<String>,
+}
+
+pub async fn run(
+    args: &Args,
+    startup: Startup,
+    frames: UnboundedReceiver<String>,
+    status: UnboundedReceiver<StatusUpdate>,
+    commands: UnboundedSender<ConnectionCommand>,
+) -> Result<(), Box<dyn Error>> {
+    let Startup {
+        url,
+        config,
+        config_path,
+        config_error,
+    } = startup;
 
-    thread::spawn(move || {
-        let rt = runtime::Runtime::new().expect("Failed to create Tokio runtime.");
-        rt.block_on(process_messages(receiver));
-    });
+    // a panic would otherwise leave the terminal in raw mode on the alternate screen,
+    // hiding the panic message. only one on this thread takes the interface down, the
+    // supervisor's is noticed by `run_app` when its channels close.
+    let ui_thread = thread::current().id();
+    let previous_hook = Arc::new(panic::take_hook());
+    let hook = Arc::clone(&previous_hook);
+    panic::set_hook(Box::new(move |info| {
+        if thread::current().id() == ui_thread {
+            let _ = restore_terminal();
+        }
+        hook(info);
+    }));
 
+    enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    if let Err(err) = execute!(stdout, EnterAlternateScreen, EnableMouseCapture) {
+        let _ = restore_terminal();
+        return Err(err.into());
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let app = App::new("jamespy client (ip-address)");
-    let res = run_app(&mut terminal, app, tick_rate);
+    let history_size = args.history_size.unwrap_or(config.history_limit);
+    let history_memory = args.history_memory.or(config.history_memory_mib);
+    let mut app = App::new(
+        "jamespy client",
+        url,
+        args.scheme(),
+        commands,
+        config,
+        config_path,
+        EventStore::new(
+            history_size,
+            history_memory.map(|mib| mib.saturating_mul(1024 * 1024)),
+        ),
+    );
+    app.load_watchlist();
+    app.load_active_filter();
+    // a config that couldn't be loaded is the more important error.
+    if config_error.is_some() {
+        app.config_error = config_error;
+    }
+    let res = run_app(&mut terminal, app, args.tick_rate(), frames, status).await;
 
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    restore_terminal()?;
+    // dropping our hook leaves the only reference to the one it replaced.
+    drop(panic::take_hook());
+    if let Ok(previous_hook) = Arc::try_unwrap(previous_hook) {
+        panic::set_hook(previous_hook);
+    }
 
     if let Err(err) = res {
-        println!("{err:?}");
+        eprintln!("jamespy-client stopped because of an error: {err}");
     }
 
     Ok(())
 }
 
-fn run_app<B: Backend>(
+/// Puts the terminal back how we found it, safe to call more than once.
+fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        Show
+    )
+}
+
+/// The one loop everything runs on: terminal input, frames and connection updates from
+/// the supervisor, redraws on every tick, and shutdown signals.
+async fn run_app<B: Backend + io::Write>(
     terminal: &mut Terminal<B>,
-    mut app: App,
+    mut app: App<'_>,
     tick_rate: Duration,
+    mut frames: UnboundedReceiver<String>,
+    mut status: UnboundedReceiver<StatusUpdate>,
 ) -> io::Result<()> {
-    let mut last_tick = Instant::now();
+    let mut input = EventStream::new();
+    let mut ticks = time::interval(tick_rate);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    let mut last_draw = Instant::now();
+    let mut mouse_captured = true;
     loop {
-        terminal.draw(|f| ui::draw(f, &mut app))?;
-
-        let timeout = tick_rate.saturating_sub(last_tick.elapsed());
-        if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Char(c) => app.on_key(c),
-                        KeyCode::Left => app.on_left(),
-                        KeyCode::Right => app.on_right(),
-                        _ => {}
-                    }
+        tokio::select! {
+            _ = ticks.tick() => {
+                app.on_tick();
+                // the status bar timers still need a redraw every second.
+                if app.dirty || last_draw.elapsed() >= REDRAW_INTERVAL {
+                    terminal.draw(|f| ui::draw(f, &mut app))?;
+                    app.dirty = false;
+                    last_draw = Instant::now();
                 }
             }
+            Some(event) = input.next() => {
+                // every input can change something on screen, as can a resize.
+                app.dirty = true;
+                on_terminal_event(&mut app, event?);
+            }
+            Some(message) = frames.recv() => {
+                process_message(&mut app, &message);
+                app.dirty = true;
+            }
+            update = status.recv() => {
+                // the supervisor only stops once told to, or if it panicked.
+                let Some(update) = update else {
+                    return Err(io::Error::other("the connection supervisor stopped"));
+                };
+                app.on_status(update);
+                app.dirty = true;
+            }
+            _ = &mut shutdown => app.should_quit = true,
         }
-        if last_tick.elapsed() >= tick_rate {
-            app.on_tick();
-            last_tick = Instant::now();
+
+        if app.config.mouse_capture != mouse_captured {
+            mouse_captured = app.config.mouse_capture;
+            if mouse_captured {
+                execute!(terminal.backend_mut(), EnableMouseCapture)?;
+            } else {
+                execute!(terminal.backend_mut(), DisableMouseCapture)?;
+            }
+        }
+        if app.bell {
+            app.bell = false;
+            let backend = terminal.backend_mut();
+            io::Write::write_all(backend, b"\x07")?;
+            io::Write::flush(backend)?;
         }
         if app.should_quit {
             return Ok(());
@@ -84,41 +198,95 @@ fn run_app<B: Backend>(
     }
 }
 
-async fn process_messages(receiver: mpsc::Receiver<String>) {
-    while let Ok(message) = receiver.recv() {
-        if let Ok(event) = serde_json::from_str::<WebSocketEvent>(&message) {
-            {
-                let mut messages = MESSAGES.lock().unwrap();
-                if messages.len() > 500 {
-                    messages.remove(0);
+fn on_terminal_event(app: &mut App, event: Event) {
+    match event {
+        // raw mode swallows the SIGINT ctrl+c would usually send.
+        Event::Key(key)
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) =>
+        {
+            app.should_quit = true;
+        }
+        Event::Key(key) if key.kind == KeyEventKind::Press => {
+            if app.connect_dialog.is_some() {
+                app.on_dialog_key(key.code);
+            } else if app.filter_editor.is_some() {
+                app.on_filter_key(key.code);
+            } else if app.search.as_ref().is_some_and(|search| search.editing) {
+                app.on_search_key(key.code);
+            } else if app.event_detail.is_some() {
+                app.on_detail_key(key.code);
+            } else {
+                match key.code {
+                    KeyCode::Char(c) => app.on_key(c),
+                    KeyCode::Left => app.on_left(),
+                    KeyCode::Right => app.on_right(),
+                    KeyCode::Up => app.scroll_up(),
+                    KeyCode::Down => app.scroll_down(),
+                    KeyCode::PageUp => app.page_up(),
+                    KeyCode::PageDown => app.page_down(),
+                    KeyCode::Home => app.scroll_to_top(),
+                    KeyCode::End => app.scroll_to_bottom(),
+                    KeyCode::Esc => app.close_search(),
+                    _ => {}
                 }
             }
-            match event {
-                WebSocketEvent::NewMessage {
-                    message,
-                    guild_name,
-                    channel_name,
-                } => {
-                    let _ = event_handlers::new_message(message, guild_name, channel_name).await;
-                }
-                WebSocketEvent::MessageEdit {
-                    old_if_available,
-                    new,
-                    event,
-                    channel_name,
-                    guild_name,
-                } => {
-                    let _ = event_handlers::message_edit(
-                        old_if_available,
-                        new,
-                        event,
-                        guild_name,
-                        channel_name,
-                    )
-                    .await;
-                }
-                _ => {}
+        }
+        Event::Mouse(mouse) => app.on_mouse(mouse),
+        _ => {}
+    }
+}
+
+fn process_message(app: &mut App, message: &str) {
+    let (payload, names) = match diagnostics::parse_event(message) {
+        Ok(mut event) => {
+            let names = event_handlers::ingest(
+                &mut event,
+                &mut app.messages,
+                &mut app.guilds,
+                &mut app.voice,
+            );
+            (Payload::Known(Box::new(event)), names)
+        }
+        Err(failure) => {
+            let error = (failure.kind != FailureKind::UnknownVariant)
+                .then(|| format!("{} at {}", failure.message, failure.path));
+            app.on_status(StatusUpdate::Unparsed(failure));
+            // still show events we don't understand, jamespy may have added them.
+            let Some((variant, data)) = diagnostics::split_tag(message) else {
+                return;
+            };
+            let payload = Payload::Unknown {
+                variant,
+                data,
+                error,
+            };
+            (payload, Names::default())
+        }
+    };
+    app.on_status(StatusUpdate::Event(StoredEvent::new(
+        payload,
+        names,
+        message.len(),
+    )));
+}
+
+/// Resolves once we're asked to stop, by SIGINT or, on unix, SIGTERM or SIGHUP.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        if let (Ok(mut terminate), Ok(mut hangup)) = (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::hangup()),
+        ) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+                _ = hangup.recv() => {}
             }
+            return;
         }
     }
+    let _ = tokio::signal::ctrl_c().await;
 }
@@ -0,0 +1,119 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    time::Instant,
+};
+
+use serde_json::error::Category;
+
+use crate::event::WebSocketEvent;
+
+/// How many parse failures are kept for the diagnostics tab.
+const MAX_FAILURES: usize = 200;
+/// How much of a failed frame is kept, in characters.
+const PAYLOAD_PREVIEW_LEN: usize = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FailureKind {
+    /// Not valid JSON at all.
+    Syntax,
+    /// The frame ended early.
+    Eof,
+    /// The event tag isn't one we know about.
+    UnknownVariant,
+    MissingField,
+    InvalidType,
+    /// Any other mismatch between the frame and our types.
+    Data,
+    Io,
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FailureKind::Syntax => "syntax",
+            FailureKind::Eof => "eof",
+            FailureKind::UnknownVariant => "unknown variant",
+            FailureKind::MissingField => "missing field",
+            FailureKind::InvalidType => "invalid type",
+            FailureKind::Data => "data",
+            FailureKind::Io => "io",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A frame that couldn't be deserialized into a [`WebSocketEvent`].
+#[derive(Debug, Clone)]
+pub struct ParseFailure {
+    pub received: Instant,
+    pub kind: FailureKind,
+    /// Where in the frame deserializing failed, e.g. `NewMessage.message.author`.
+    pub path: String,
+    /// The event tag, if the frame looks like an event at all.
+    pub variant: Option<String>,
+    pub message: String,
+    /// The start of the raw frame.
+    pub payload: String,
+}
+
+/// Deserializes a frame, keeping enough about a failure to work out why.
+pub fn parse_event(raw: &str) -> Result<WebSocketEvent, ParseFailure> {
+    let deserializer = &mut serde_json::Deserializer::from_str(raw);
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let path = err.path().to_string();
+        let inner = err.into_inner();
+        let message = inner.to_string();
+
+        let kind = match inner.classify() {
+            Category::Syntax => FailureKind::Syntax,
+            Category::Eof => FailureKind::Eof,
+            Category::Io => FailureKind::Io,
+            Category::Data if message.starts_with("unknown variant") => FailureKind::UnknownVariant,
+            Category::Data if message.starts_with("missing field") => FailureKind::MissingField,
+            Category::Data if message.starts_with("invalid type") => FailureKind::InvalidType,
+            Category::Data => FailureKind::Data,
+        };
+
+        ParseFailure {
+            received: Instant::now(),
+            kind,
+            path,
+            variant: variant_name(raw),
+            message,
+            payload: raw.chars().take(PAYLOAD_PREVIEW_LEN).collect(),
+        }
+    })
+}
+
+/// Events are externally tagged, so the tag is the only key of the top level object.
+fn variant_name(raw: &str) -> Option<String> {
+    split_tag(raw).map(|(variant, _)| variant)
+}
+
+/// Splits an externally tagged event into its tag and data without knowing the variant.
+pub fn split_tag(raw: &str) -> Option<(String, serde_json::Value)> {
+    let value: serde_json::Value = serde_json::from_str(raw).ok()?;
+    let serde_json::Value::Object(object) = value else {
+        return None;
+    };
+    if object.len() != 1 {
+        return None;
+    }
+    object.into_iter().next()
+}
+
+/// Recent parse failures and how often each kind has happened.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    pub failures: VecDeque<ParseFailure>,
+    pub counts: BTreeMap<FailureKind, u64>,
+}
+
+impl Diagnostics {
+    pub fn record(&mut self, failure: ParseFailure) {
+        *self.counts.entry(failure.kind).or_default() += 1;
+        self.failures.push_front(failure);
+        self.failures.truncate(MAX_FAILURES);
+    }
+}
@@ -0,0 +1,288 @@
+use crossterm::event::KeyCode;
+use url::Url;
+
+use crate::{
+    cli::{Scheme, DEFAULT_PORT},
+    filter::{self, Filter, Rule},
+};
+
+/// How many servers are remembered in the recent servers list.
+pub const MAX_RECENT_SERVERS: usize = 10;
+
+/// The connect prompt, shown on startup failures or when opened by the user.
+pub struct ConnectDialog {
+    pub input: String,
+    pub selected: Option<usize>,
+    pub error: Option<String>,
+}
+
+/// What the dialog wants the `App` to do after a key press.
+pub enum DialogAction {
+    None,
+    Close,
+    Connect(String),
+}
+
+impl ConnectDialog {
+    pub fn new(current: &str) -> ConnectDialog {
+        ConnectDialog {
+            input: current.to_string(),
+            selected: None,
+            error: None,
+        }
+    }
+
+    pub fn on_key(&mut self, code: KeyCode, recent: &[String], scheme: Scheme) -> DialogAction {
+        match code {
+            KeyCode::Esc => return DialogAction::Close,
+            KeyCode::Enter => match normalize_address(&self.input, scheme) {
+                Ok(url) => return DialogAction::Connect(url),
+                Err(err) => self.error = Some(err),
+            },
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.selected = None;
+                self.error = None;
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.selected = None;
+                self.error = None;
+            }
+            KeyCode::Up if !recent.is_empty() => {
+                let index = match self.selected {
+                    Some(0) | None => recent.len() - 1,
+                    Some(index) => index - 1,
+                };
+                self.select(index, recent);
+            }
+            KeyCode::Down if !recent.is_empty() => {
+                let index = match self.selected {
+                    Some(index) if index + 1 < recent.len() => index + 1,
+                    _ => 0,
+                };
+                self.select(index, recent);
+            }
+            _ => {}
+        }
+        DialogAction::None
+    }
+
+    fn select(&mut self, index: usize, recent: &[String]) {
+        self.selected = Some(index);
+        self.input = recent[index].clone();
+        self.error = None;
+    }
+}
+
+/// The filter editor, for writing rules and picking or deleting saved filters.
+pub struct FilterEditor {
+    pub name: String,
+    pub rules: String,
+    pub focus: FilterField,
+    pub selected: Option<usize>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FilterField {
+    Name,
+    Rules,
+}
+
+/// What the filter editor wants the `App` to do after a key press.
+pub enum FilterAction {
+    None,
+    Close,
+    /// Use these rules, saving them under `name` unless it's empty.
+    Apply {
+        name: String,
+        rules: Vec<Rule>,
+    },
+    /// Forget the saved filter at this index.
+    Delete(usize),
+}
+
+impl FilterEditor {
+    pub fn new(name: &str, rules: &[Rule]) -> FilterEditor {
+        FilterEditor {
+            name: name.to_string(),
+            rules: filter::format_rules(rules),
+            focus: FilterField::Rules,
+            selected: None,
+            error: None,
+        }
+    }
+
+    pub fn on_key(&mut self, code: KeyCode, saved: &[Filter]) -> FilterAction {
+        match code {
+            KeyCode::Esc => return FilterAction::Close,
+            KeyCode::Enter => match filter::parse_rules(&self.rules) {
+                Ok(rules) => {
+                    return FilterAction::Apply {
+                        name: self.name.trim().to_string(),
+                        rules,
+                    }
+                }
+                Err(err) => self.error = Some(err),
+            },
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    FilterField::Name => FilterField::Rules,
+                    FilterField::Rules => FilterField::Name,
+                };
+            }
+            KeyCode::Char(c) => {
+                self.field().push(c);
+                self.error = None;
+            }
+            KeyCode::Backspace => {
+                self.field().pop();
+                self.error = None;
+            }
+            KeyCode::Delete => {
+                if let Some(index) = self.selected.take() {
+                    self.error = None;
+                    return FilterAction::Delete(index);
+                }
+            }
+            KeyCode::Up if !saved.is_empty() => {
+                let index = match self.selected {
+                    Some(0) | None => saved.len() - 1,
+                    Some(index) => index - 1,
+                };
+                self.select(index, saved);
+            }
+            KeyCode::Down if !saved.is_empty() => {
+                let index = match self.selected {
+                    Some(index) if index + 1 < saved.len() => index + 1,
+                    _ => 0,
+                };
+                self.select(index, saved);
+            }
+            _ => {}
+        }
+        FilterAction::None
+    }
+
+    fn field(&mut self) -> &mut String {
+        match self.focus {
+            FilterField::Name => &mut self.name,
+            FilterField::Rules => &mut self.rules,
+        }
+    }
+
+    fn select(&mut self, index: usize, saved: &[Filter]) {
+        self.selected = Some(index);
+        self.name = saved[index].name.clone();
+        self.rules = filter::format_rules(&saved[index].rules);
+        self.error = None;
+    }
+}
+
+/// Turns what the user typed into a websocket url, defaulting to `scheme`
+/// when only `host:port` is given and to [`DEFAULT_PORT`] when there's no port.
+pub fn normalize_address(input: &str, scheme: Scheme) -> Result<String, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(String::from("enter an address to connect to"));
+    }
+
+    let with_scheme = if input.contains("://") {
+        input.to_string()
+    } else {
+        format!("{scheme}://{input}")
+    };
+    // tungstenite would otherwise connect to 80 or 443, not where jamespy listens.
+    let with_scheme = match with_scheme.split_once("://") {
+        Some((scheme, rest)) => {
+            let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+            let (authority, path) = rest.split_at(end);
+            if has_port(authority) {
+                with_scheme
+            } else {
+                format!("{scheme}://{authority}:{DEFAULT_PORT}{path}")
+            }
+        }
+        None => with_scheme,
+    };
+
+    let url = Url::parse(&with_scheme).map_err(|err| format!("invalid address: {err}"))?;
+    match url.scheme() {
+        "ws" | "wss" => {}
+        other => return Err(format!("unsupported scheme `{other}`, expected ws or wss")),
+    }
+    if url.host_str().is_none() {
+        return Err(String::from("the address is missing a host"));
+    }
+
+    Ok(with_scheme)
+}
+
+/// Whether `authority` ends in a port, looking past any user info and IPv6 brackets.
+fn has_port(authority: &str) -> bool {
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    host.rsplit_once(':')
+        .is_some_and(|(_, port)| !port.is_empty() && port.bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+/// Moves `server` to the front of `recent`, keeping at most [`MAX_RECENT_SERVERS`].
+pub fn remember_server(recent: &mut Vec<String>, server: &str) {
+    recent.retain(|s| s != server);
+    recent.insert(0, server.to_string());
+    recent.truncate(MAX_RECENT_SERVERS);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_host_gets_the_default_port() {
+        assert_eq!(
+            normalize_address("192.168.0.113", Scheme::Ws).unwrap(),
+            "ws://192.168.0.113:8080"
+        );
+        assert_eq!(
+            normalize_address(" localhost ", Scheme::Wss).unwrap(),
+            "wss://localhost:8080"
+        );
+        assert_eq!(
+            normalize_address("[::1]", Scheme::Ws).unwrap(),
+            "ws://[::1]:8080"
+        );
+    }
+
+    #[test]
+    fn explicit_port_is_kept() {
+        assert_eq!(
+            normalize_address("192.168.0.113:9000", Scheme::Ws).unwrap(),
+            "ws://192.168.0.113:9000"
+        );
+        assert_eq!(
+            normalize_address("[::1]:9000", Scheme::Ws).unwrap(),
+            "ws://[::1]:9000"
+        );
+    }
+
+    #[test]
+    fn explicit_scheme_is_kept() {
+        assert_eq!(
+            normalize_address("wss://example.com:443/events", Scheme::Ws).unwrap(),
+            "wss://example.com:443/events"
+        );
+        assert_eq!(
+            normalize_address("wss://example.com/events", Scheme::Ws).unwrap(),
+            "wss://example.com:8080/events"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_addresses() {
+        assert!(normalize_address("http://example.com", Scheme::Ws)
+            .unwrap_err()
+            .starts_with("unsupported scheme"));
+        assert!(normalize_address("   ", Scheme::Ws).is_err());
+        assert!(normalize_address("ws://", Scheme::Ws).is_err());
+    }
+}
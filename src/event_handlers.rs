@@ -1,12 +1,395 @@
-use crate::{app::MESSAGES, Error};
+use crate::{
+    cache::{GuildRegistry, MessageCache, NameResolver, Names},
+    config::Config,
+    event::WebSocketEvent,
+    store::{self, Payload, StoredEvent},
+    voice::{self, VoiceOccupancy, VoiceTransition},
+};
 use ratatui::prelude::*;
-use serenity::all::{Message, MessageUpdateEvent};
+use serenity::all::{
+    AutoArchiveDuration, Channel, ChannelId, Guild, GuildChannel, GuildId, GuildMemberUpdateEvent,
+    Member, Message, MessageId, MessageUpdateEvent, PartialGuildChannel, PermissionOverwrite,
+    PermissionOverwriteType, Permissions, Reaction, ReactionType, RoleId, Timestamp, User,
+    VoiceState,
+};
+use std::{fmt::Display, time::Duration};
 
-pub async fn new_message(
-    message: Message,
-    guild_name: String,
-    channel_name: String,
-) -> Result<(), Box<dyn Error>> {
+/// Updates the caches from an event before it's stored, returning the names it refers to.
+///
+/// Events are only rendered when drawn, so anything they'd lose from a cache by then is
+/// copied into them here, and names are looked up before the event changes the registry.
+pub fn ingest(
+    event: &mut WebSocketEvent,
+    messages: &mut MessageCache,
+    guilds: &mut GuildRegistry,
+    voice: &mut VoiceOccupancy,
+) -> Names {
+    let names = resolve_names(event, guilds);
+
+    match event {
+        WebSocketEvent::NewMessage { message, .. }
+        | WebSocketEvent::MessageEdit {
+            new: Some(message), ..
+        } => {
+            messages.insert(message.clone());
+        }
+        WebSocketEvent::MessageDelete {
+            deleted_message_id,
+            message,
+            ..
+        } => {
+            // jamespy only sends the message if it was in its own cache, we might still have it.
+            let cached = messages.remove(*deleted_message_id);
+            if message.is_none() {
+                *message = cached;
+            }
+        }
+        WebSocketEvent::ChannelCreate { channel, .. }
+        | WebSocketEvent::ChannelUpdate { new: channel, .. }
+        | WebSocketEvent::ThreadCreate {
+            thread: channel, ..
+        }
+        | WebSocketEvent::ThreadUpdate { new: channel, .. } => {
+            guilds.upsert_channel(channel);
+        }
+        WebSocketEvent::ChannelDelete { channel, .. } => {
+            guilds.remove_channel(channel.guild_id, channel.id);
+        }
+        WebSocketEvent::ThreadDelete { thread, .. } => {
+            guilds.remove_channel(thread.guild_id, thread.id);
+        }
+        WebSocketEvent::GuildCreate { guild, .. } => guilds.insert(guild),
+        WebSocketEvent::VoiceStateUpdate {
+            new,
+            new_guild_name,
+            new_channel_name,
+            user_name,
+            ..
+        } => {
+            let user_name = user_name.clone().unwrap_or_else(|| new.user_id.to_string());
+            voice.apply(
+                new,
+                new_guild_name.as_deref(),
+                new_channel_name.as_deref(),
+                &user_name,
+            );
+        }
+        _ => {}
+    }
+
+    names
+}
+
+/// Looks up the names of everything `event` refers to that its renderer shows.
+fn resolve_names(event: &WebSocketEvent, guilds: &GuildRegistry) -> Names {
+    let (guild_id, channel_id, _) = store::event_ids(event);
+    let mut resolver = NameResolver::new(guilds, guild_id);
+    if let Some(channel_id) = channel_id {
+        resolver.channel(channel_id);
+    }
+
+    let channel = |resolver: &mut NameResolver, channel: &GuildChannel| {
+        if let Some(parent_id) = channel.parent_id {
+            resolver.channel(parent_id);
+        }
+        for overwrite in &channel.permission_overwrites {
+            if let PermissionOverwriteType::Role(role_id) = overwrite.kind {
+                resolver.role(role_id);
+            }
+        }
+    };
+    match event {
+        WebSocketEvent::ChannelCreate { channel: new, .. }
+        | WebSocketEvent::ChannelDelete { channel: new, .. }
+        | WebSocketEvent::ThreadCreate { thread: new, .. } => channel(&mut resolver, new),
+        WebSocketEvent::ChannelUpdate { old, new, .. }
+        | WebSocketEvent::ThreadUpdate { old, new, .. } => {
+            channel(&mut resolver, new);
+            if let Some(old) = old {
+                channel(&mut resolver, old);
+            }
+        }
+        WebSocketEvent::ThreadDelete {
+            thread,
+            full_thread_data,
+            ..
+        } => {
+            resolver.channel(thread.parent_id);
+            if let Some(full_thread) = full_thread_data {
+                channel(&mut resolver, full_thread);
+            }
+        }
+        WebSocketEvent::GuildMemberUpdate {
+            old_if_available,
+            new,
+            event,
+            ..
+        } => {
+            let roles = old_if_available
+                .iter()
+                .chain(new)
+                .flat_map(|member| &member.roles)
+                .chain(&event.roles);
+            for role_id in roles {
+                resolver.role(*role_id);
+            }
+        }
+        WebSocketEvent::ReactionAdd {
+            add_reaction: reaction,
+            ..
+        }
+        | WebSocketEvent::ReactionRemove {
+            removed_reaction: reaction,
+            ..
+        } => {
+            if let ReactionType::Custom { id, .. } = reaction.emoji {
+                resolver.emoji(id);
+            }
+        }
+        _ => {}
+    }
+    resolver.names
+}
+
+/// The lines for one event, or a burst of reactions collapsed into one.
+pub struct RenderedEvent {
+    /// The sequence number of the (first) event these lines are for.
+    pub seq: u64,
+    pub lines: Vec<text::Line<'static>>,
+}
+
+/// Renders `events` in order, leaving out events with nothing to show.
+pub fn render_events<'a>(
+    events: impl IntoIterator<Item = &'a StoredEvent>,
+    config: &Config,
+) -> Vec<RenderedEvent> {
+    let mut rendered: Vec<RenderedEvent> = Vec::new();
+    let mut burst: Option<ReactionBurst> = None;
+
+    for event in events {
+        if let (true, Some(parts)) = (config.collapse_reactions, reaction_parts(event)) {
+            let (reaction, added, user_name, guild_name, channel_name) = parts;
+            // only keep collapsing while the burst is still the newest entry.
+            let continues = burst.as_ref().is_some_and(|burst| {
+                burst.message_id == reaction.message_id
+                    && event.received.unix_timestamp() - burst.started.unix_timestamp()
+                        <= REACTION_BURST_WINDOW.as_secs() as i64
+                    && rendered.last().map(|entry| entry.seq) == Some(burst.seq)
+            });
+            if !continues {
+                burst = None;
+                rendered.push(RenderedEvent {
+                    seq: event.seq,
+                    lines: Vec::new(),
+                });
+            }
+            let burst = burst.get_or_insert_with(|| ReactionBurst {
+                seq: event.seq,
+                message_id: reaction.message_id,
+                started: event.received,
+                changes: Vec::new(),
+                users: Vec::new(),
+            });
+            burst
+                .changes
+                .push((added, format_emoji(&reaction.emoji, &event.names)));
+            if !burst.users.iter().any(|user| user == user_name) {
+                burst.users.push(user_name.to_string());
+            }
+            if let Some(entry) = rendered.last_mut() {
+                entry.lines = vec![burst.line(guild_name, channel_name)];
+            }
+            continue;
+        }
+
+        let lines = render_event(event, config);
+        if !lines.is_empty() {
+            rendered.push(RenderedEvent {
+                seq: event.seq,
+                lines,
+            });
+        }
+    }
+
+    rendered
+}
+
+/// Whether `event` is recent enough that it might still be collapsed into a reaction
+/// burst starting with `first`, so the burst has to be rendered again along with it.
+pub fn may_join_burst(first: &StoredEvent, event: &StoredEvent, config: &Config) -> bool {
+    config.collapse_reactions
+        && reaction_parts(first).is_some()
+        && event.received.unix_timestamp() - first.received.unix_timestamp()
+            <= REACTION_BURST_WINDOW.as_secs() as i64
+}
+
+/// The lines for a single event, empty if there's nothing worth showing.
+pub fn render_event(event: &StoredEvent, config: &Config) -> Vec<text::Line<'static>> {
+    let event_data = match &event.payload {
+        Payload::Known(event_data) => event_data,
+        Payload::Unknown {
+            variant,
+            data,
+            error,
+        } => {
+            return unknown_event(
+                variant,
+                data,
+                error.as_deref(),
+                config.expand_unknown_events,
+            )
+        }
+    };
+
+    match event_data.as_ref() {
+        WebSocketEvent::NewMessage {
+            message,
+            guild_name,
+            channel_name,
+        } => new_message(message, guild_name, channel_name, event.watched),
+        WebSocketEvent::MessageEdit {
+            old_if_available,
+            new,
+            event: update,
+            guild_name,
+            channel_name,
+        } => {
+            let mut lines = message_edit(
+                old_if_available.as_ref(),
+                new.as_ref(),
+                update,
+                guild_name.as_deref(),
+                channel_name.as_deref(),
+            );
+            if event.watched {
+                mark_watched(&mut lines);
+            }
+            lines
+        }
+        WebSocketEvent::MessageDelete {
+            channel_id,
+            deleted_message_id,
+            guild_id,
+            message,
+            guild_name,
+            channel_name,
+        } => message_delete(
+            *channel_id,
+            *deleted_message_id,
+            *guild_id,
+            message.as_ref(),
+            guild_name,
+            channel_name,
+        ),
+        WebSocketEvent::ChannelCreate {
+            channel,
+            guild_name,
+        } => channel_create(channel, guild_name, &event.names),
+        WebSocketEvent::ChannelUpdate {
+            old,
+            new,
+            guild_name,
+        } => channel_update(old.as_ref(), new, guild_name, &event.names),
+        WebSocketEvent::ChannelDelete {
+            channel,
+            guild_name,
+        } => channel_delete(channel, guild_name, &event.names),
+        WebSocketEvent::ThreadCreate { thread, guild_name } => {
+            thread_create(thread, guild_name, &event.names)
+        }
+        WebSocketEvent::ThreadUpdate {
+            old,
+            new,
+            parent_channel,
+            guild_name,
+        } => thread_update(
+            old.as_ref(),
+            new,
+            parent_channel.as_ref(),
+            guild_name,
+            &event.names,
+        ),
+        WebSocketEvent::ThreadDelete {
+            thread,
+            full_thread_data,
+            guild_name,
+        } => thread_delete(thread, full_thread_data.as_ref(), guild_name, &event.names),
+        WebSocketEvent::GuildCreate { guild, is_new } => guild_create(guild, *is_new),
+        WebSocketEvent::GuildMemberAddition {
+            new_member,
+            guild_name,
+        } => guild_member_addition(new_member, guild_name, event.received),
+        WebSocketEvent::GuildMemberRemoval {
+            user, guild_name, ..
+        } => guild_member_removal(user, guild_name),
+        WebSocketEvent::GuildMemberUpdate {
+            old_if_available,
+            new,
+            event: update,
+            guild_name,
+        } => guild_member_update(
+            old_if_available.as_ref(),
+            new.as_ref(),
+            update,
+            guild_name,
+            event.received,
+            &event.names,
+        ),
+        WebSocketEvent::ReactionAdd {
+            add_reaction: reaction,
+            user_name,
+            guild_name,
+            channel_name,
+        } => vec![reaction_line(
+            reaction,
+            true,
+            user_name,
+            guild_name,
+            channel_name,
+            &event.names,
+        )],
+        WebSocketEvent::ReactionRemove {
+            removed_reaction: reaction,
+            user_name,
+            guild_name,
+            channel_name,
+        } => vec![reaction_line(
+            reaction,
+            false,
+            user_name,
+            guild_name,
+            channel_name,
+            &event.names,
+        )],
+        WebSocketEvent::VoiceStateUpdate {
+            old,
+            new,
+            old_guild_name,
+            old_channel_name,
+            new_guild_name,
+            new_channel_name,
+            user_name,
+        } => voice_state_update(
+            old.as_ref(),
+            new,
+            // jamespy leaves the guild out sometimes, the registry might have it.
+            new_guild_name
+                .as_deref()
+                .or(old_guild_name.as_deref())
+                .or(event.names.guild.as_deref()),
+            old_channel_name.as_deref(),
+            new_channel_name.as_deref(),
+            user_name.as_deref(),
+        ),
+    }
+}
+
+fn new_message(
+    message: &Message,
+    guild_name: &str,
+    channel_name: &str,
+    watched: bool,
+) -> Vec<text::Line<'static>> {
     let attachments = message.attachments.clone();
     let attachments_fmt: Option<String> = if !attachments.is_empty() {
         let attachment_names: Vec<String> = attachments
@@ -34,7 +417,7 @@ pub async fn new_message(
     let total_lines = message.content.lines().count();
 
     for (index, line) in message.content.lines().enumerate() {
-        let mut formatted_line = vec![Span::raw(format!("{}", line))];
+        let mut formatted_line = vec![Span::raw(line.to_string())];
 
         if index == 0 {
             formatted_line.insert(
@@ -60,7 +443,7 @@ pub async fn new_message(
 
         lines.push(text::Line::from(formatted_line));
     }
-    
+
     if message.content.is_empty() {
         lines.push(text::Line::from(vec![
             Span::styled(
@@ -68,32 +451,56 @@ pub async fn new_message(
                 Style::default().fg(Color::DarkGray),
             ),
             Span::raw(format!("{}:", message.author.name)),
-            Span::styled(format!("{}{}" ,attachments_fmt.as_deref().unwrap_or(""), embeds_fmt.as_deref().unwrap_or("") ),
-            Style::default().fg(Color::Cyan))
+            Span::styled(
+                format!(
+                    "{}{}",
+                    attachments_fmt.as_deref().unwrap_or(""),
+                    embeds_fmt.as_deref().unwrap_or("")
+                ),
+                Style::default().fg(Color::Cyan),
+            ),
         ]));
     }
 
-    let msg = lines;
+    if watched {
+        mark_watched(&mut lines);
+    }
+    lines
+}
 
-    // handle bad words
-    let mut handle = MESSAGES.lock().unwrap();
-    handle.push(msg);
-    Ok(())
+/// Makes a message that matched the watchlist stand out: bold, with a marker in front.
+fn mark_watched(lines: &mut [text::Line<'static>]) {
+    for line in lines.iter_mut() {
+        for span in &mut line.spans {
+            span.style = span.style.add_modifier(Modifier::BOLD);
+        }
+    }
+    if let Some(first) = lines.first_mut() {
+        first.spans.insert(
+            0,
+            Span::styled(
+                "! ",
+                Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        );
+    }
 }
 
-pub async fn message_edit(
-    old_if_available: Option<Message>,
-    new: Option<Message>,
-    event: MessageUpdateEvent,
-    guild_name: Option<String>,
-    channel_name: Option<String>,
-) -> Result<(), Box<dyn Error>> {
+fn message_edit(
+    old_if_available: Option<&Message>,
+    new: Option<&Message>,
+    event: &MessageUpdateEvent,
+    guild_name: Option<&str>,
+    channel_name: Option<&str>,
+) -> Vec<text::Line<'static>> {
     // Currently the guild_names and channel_names will always be a value because its handled within the bot.
     // eventualy I will switch that.
     match (old_if_available, new) {
         (Some(old_message), Some(new_message)) => {
             if new_message.author.bot {
-                return Ok(());
+                return Vec::new();
             }
 
             if old_message.content != new_message.content {
@@ -127,8 +534,8 @@ pub async fn message_edit(
                     Span::styled(
                         format!(
                             "[{}] [#{}] A message by ",
-                            guild_name.unwrap(),
-                            channel_name.unwrap()
+                            guild_name.unwrap_or("Unknown guild"),
+                            channel_name.unwrap_or("unknown channel")
                         ),
                         Style::default().fg(Color::Cyan),
                     ),
@@ -173,22 +580,883 @@ pub async fn message_edit(
                 }
 
                 // Maybe check old embeds and or attachments in the future, requires a big rewrite to this.
-                let mut handle = MESSAGES.lock().unwrap();
-                handle.push(msg);
+                return msg;
             }
+            Vec::new()
         }
-        (None, None) => {
-            let msg = vec![text::Line::from(vec![Span::styled(
+        (None, None) => vec![text::Line::from(vec![Span::styled(
+            format!(
+                "A message (ID:{}) was edited but was not in cache",
+                event.id
+            ),
+            Style::default().fg(Color::Cyan),
+        )])],
+        _ => Vec::new(),
+    }
+}
+
+fn message_delete(
+    channel_id: ChannelId,
+    deleted_message_id: MessageId,
+    guild_id: Option<GuildId>,
+    message: Option<&Message>,
+    guild_name: &str,
+    channel_name: &str,
+) -> Vec<text::Line<'static>> {
+    let location = Span::styled(
+        format!("[{}] [#{}] ", guild_name, channel_name),
+        Style::default().fg(Color::DarkGray),
+    );
+    let deleted_style = Style::default().fg(Color::Red);
+
+    let Some(message) = message else {
+        let guild = guild_id.map_or(String::new(), |id| format!(" in guild {id}"));
+        return vec![text::Line::from(vec![
+            location,
+            Span::styled(
                 format!(
-                    "A message (ID:{}) was edited but was not in cache",
-                    event.id
+                    "A message (ID:{}) was deleted in {}{} but was not in cache",
+                    deleted_message_id, channel_id, guild
                 ),
-                Style::default().fg(Color::Cyan),
-            )])];
-            let mut handle = MESSAGES.lock().unwrap();
-            handle.push(msg);
+                deleted_style,
+            ),
+        ])];
+    };
+
+    let mut msg = vec![text::Line::from(vec![
+        location,
+        Span::styled("A message by ", deleted_style),
+        Span::raw(message.author.name.clone()),
+        Span::styled(" was deleted:", deleted_style),
+    ])];
+
+    let content_style = deleted_style.add_modifier(Modifier::CROSSED_OUT);
+    for (index, line) in message.content.lines().enumerate() {
+        let prefix = if index == 0 {
+            format!("{}: ", message.author.name)
+        } else {
+            String::new()
+        };
+        msg.push(text::Line::from(vec![
+            Span::styled(prefix, deleted_style),
+            Span::styled(line.to_string(), content_style),
+        ]));
+    }
+
+    for attachment in &message.attachments {
+        msg.push(text::Line::from(Span::styled(
+            format!("<{}> {}", attachment.filename, attachment.url),
+            Style::default().fg(Color::Cyan),
+        )));
+    }
+
+    msg
+}
+
+fn channel_create(
+    channel: &GuildChannel,
+    guild_name: &str,
+    names: &Names,
+) -> Vec<text::Line<'static>> {
+    vec![channel_lifecycle_line(
+        channel,
+        guild_name,
+        names,
+        "created",
+        Color::Green,
+    )]
+}
+
+fn channel_delete(
+    channel: &GuildChannel,
+    guild_name: &str,
+    names: &Names,
+) -> Vec<text::Line<'static>> {
+    vec![channel_lifecycle_line(
+        channel,
+        guild_name,
+        names,
+        "deleted",
+        Color::Red,
+    )]
+}
+
+fn channel_lifecycle_line(
+    channel: &GuildChannel,
+    guild_name: &str,
+    names: &Names,
+    action: &str,
+    color: Color,
+) -> text::Line<'static> {
+    let category = channel.parent_id.map_or(String::new(), |id| {
+        format!(" in category {}", channel_label(names, id))
+    });
+    text::Line::from(vec![
+        Span::styled(
+            format!("[{}] ", guild_name),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::styled(
+            format!("A {} channel ", channel.kind.name()),
+            Style::default().fg(color),
+        ),
+        Span::raw(format!("#{}", channel.name)),
+        Span::styled(
+            format!(" was {action}{category}"),
+            Style::default().fg(color),
+        ),
+    ])
+}
+
+fn channel_update(
+    old: Option<&GuildChannel>,
+    new: &GuildChannel,
+    guild_name: &str,
+    names: &Names,
+) -> Vec<text::Line<'static>> {
+    let update_style = Style::default().fg(Color::Yellow);
+    let location = Span::styled(
+        format!("[{}] [#{}] ", guild_name, new.name),
+        Style::default().fg(Color::DarkGray),
+    );
+
+    let Some(old) = old else {
+        return vec![text::Line::from(vec![
+            location,
+            Span::styled(
+                "The channel was updated but the old version was not in cache",
+                update_style,
+            ),
+        ])];
+    };
+
+    let mut changes = Vec::new();
+    if old.name != new.name {
+        changes.push(diff_line("name", &old.name, &new.name));
+    }
+    if old.topic != new.topic {
+        changes.push(diff_line(
+            "topic",
+            old.topic.as_deref().unwrap_or("none"),
+            new.topic.as_deref().unwrap_or("none"),
+        ));
+    }
+    if old.nsfw != new.nsfw {
+        changes.push(diff_line("nsfw", old.nsfw, new.nsfw));
+    }
+    if old.rate_limit_per_user != new.rate_limit_per_user {
+        changes.push(diff_line(
+            "slowmode",
+            format!("{}s", old.rate_limit_per_user.unwrap_or(0)),
+            format!("{}s", new.rate_limit_per_user.unwrap_or(0)),
+        ));
+    }
+    if old.position != new.position {
+        changes.push(diff_line("position", old.position, new.position));
+    }
+    if old.parent_id != new.parent_id {
+        changes.push(diff_line(
+            "category",
+            old.parent_id
+                .map_or(String::from("none"), |id| channel_label(names, id)),
+            new.parent_id
+                .map_or(String::from("none"), |id| channel_label(names, id)),
+        ));
+    }
+    changes.extend(overwrite_changes(
+        names,
+        &old.permission_overwrites,
+        &new.permission_overwrites,
+    ));
+
+    if changes.is_empty() {
+        return Vec::new();
+    }
+
+    let mut msg = vec![text::Line::from(vec![
+        location,
+        Span::styled("The channel was updated:", update_style),
+    ])];
+    msg.extend(changes);
+    msg
+}
+
+fn diff_line(field: &str, old: impl Display, new: impl Display) -> text::Line<'static> {
+    text::Line::from(vec![
+        Span::styled(format!("  {field}: "), Style::default().fg(Color::Yellow)),
+        Span::styled(old.to_string(), Style::default().fg(Color::Red)),
+        Span::raw(" -> "),
+        Span::styled(new.to_string(), Style::default().fg(Color::Green)),
+    ])
+}
+
+fn permission_names(permissions: Permissions) -> String {
+    permissions
+        .iter_names()
+        .map(|(name, _)| name.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn overwrite_target(names: &Names, kind: PermissionOverwriteType) -> String {
+    match kind {
+        PermissionOverwriteType::Member(id) => format!("member {id}"),
+        PermissionOverwriteType::Role(id) => role_label(names, id),
+        _ => String::from("unknown"),
+    }
+}
+
+/// `#name` if the channel was in the guild registry, otherwise its ID.
+fn channel_label(names: &Names, channel_id: ChannelId) -> String {
+    match names.channel(channel_id) {
+        Some(name) => format!("#{name}"),
+        None => format!("channel {channel_id}"),
+    }
+}
+
+/// `@name` if the role was in the guild registry, otherwise its ID.
+fn role_label(names: &Names, role_id: RoleId) -> String {
+    match names.role(role_id) {
+        Some(name) => format!("@{name}"),
+        None => format!("role {role_id}"),
+    }
+}
+
+fn overwrite_changes(
+    names: &Names,
+    old: &[PermissionOverwrite],
+    new: &[PermissionOverwrite],
+) -> Vec<text::Line<'static>> {
+    let mut lines = Vec::new();
+    let label = Style::default().fg(Color::Yellow);
+
+    for overwrite in new {
+        let target = overwrite_target(names, overwrite.kind);
+        match old.iter().find(|o| o.kind == overwrite.kind) {
+            None => lines.push(text::Line::from(vec![
+                Span::styled("  overwrite added: ", label),
+                Span::styled(
+                    format!(
+                        "{target} allow [{}] deny [{}]",
+                        permission_names(overwrite.allow),
+                        permission_names(overwrite.deny)
+                    ),
+                    Style::default().fg(Color::Green),
+                ),
+            ])),
+            Some(previous) if previous != overwrite => {
+                lines.push(diff_line(
+                    &format!("{target} allow"),
+                    format!("[{}]", permission_names(previous.allow)),
+                    format!("[{}]", permission_names(overwrite.allow)),
+                ));
+                lines.push(diff_line(
+                    &format!("{target} deny"),
+                    format!("[{}]", permission_names(previous.deny)),
+                    format!("[{}]", permission_names(overwrite.deny)),
+                ));
+            }
+            Some(_) => {}
         }
-        _ => {}
     }
-    Ok(())
+
+    for overwrite in old {
+        if !new.iter().any(|o| o.kind == overwrite.kind) {
+            lines.push(text::Line::from(vec![
+                Span::styled("  overwrite removed: ", label),
+                Span::styled(
+                    overwrite_target(names, overwrite.kind),
+                    Style::default().fg(Color::Red),
+                ),
+            ]));
+        }
+    }
+
+    lines
+}
+
+fn thread_create(
+    thread: &GuildChannel,
+    guild_name: &str,
+    names: &Names,
+) -> Vec<text::Line<'static>> {
+    let parent = thread
+        .parent_id
+        .map_or(String::from("an unknown channel"), |id| {
+            channel_label(names, id)
+        });
+    let archive = thread
+        .thread_metadata
+        .map(|metadata| {
+            format!(
+                " (auto-archives after {})",
+                archive_duration(metadata.auto_archive_duration)
+            )
+        })
+        .unwrap_or_default();
+
+    vec![text::Line::from(vec![
+        Span::styled(
+            format!("[{}] ", guild_name),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::styled("A thread ", Style::default().fg(Color::Green)),
+        Span::raw(thread.name.clone()),
+        Span::styled(
+            format!(" was created in {parent}{archive}"),
+            Style::default().fg(Color::Green),
+        ),
+    ])]
+}
+
+fn thread_update(
+    old: Option<&GuildChannel>,
+    new: &GuildChannel,
+    parent_channel: Option<&Channel>,
+    guild_name: &str,
+    names: &Names,
+) -> Vec<text::Line<'static>> {
+    let parent = match parent_channel {
+        Some(Channel::Guild(parent)) => format!("#{}", parent.name),
+        _ => new.parent_id.map_or(String::from("unknown channel"), |id| {
+            channel_label(names, id)
+        }),
+    };
+    let update_style = Style::default().fg(Color::Yellow);
+    let location = Span::styled(
+        format!("[{}] [{}] ", guild_name, parent),
+        Style::default().fg(Color::DarkGray),
+    );
+
+    let Some(old) = old else {
+        return vec![text::Line::from(vec![
+            location,
+            Span::styled("The thread ", update_style),
+            Span::raw(new.name.clone()),
+            Span::styled(
+                " was updated but the old version was not in cache",
+                update_style,
+            ),
+        ])];
+    };
+
+    let mut changes = Vec::new();
+    if old.name != new.name {
+        changes.push(diff_line("name", &old.name, &new.name));
+    }
+    if let (Some(old_metadata), Some(new_metadata)) = (old.thread_metadata, new.thread_metadata) {
+        if old_metadata.archived != new_metadata.archived {
+            changes.push(state_line(new_metadata.archived, "archived", "unarchived"));
+        }
+        if old_metadata.locked != new_metadata.locked {
+            changes.push(state_line(new_metadata.locked, "locked", "unlocked"));
+        }
+        if old_metadata.auto_archive_duration != new_metadata.auto_archive_duration {
+            changes.push(diff_line(
+                "auto-archive",
+                archive_duration(old_metadata.auto_archive_duration),
+                archive_duration(new_metadata.auto_archive_duration),
+            ));
+        }
+    }
+    if old.rate_limit_per_user != new.rate_limit_per_user {
+        changes.push(diff_line(
+            "slowmode",
+            format!("{}s", old.rate_limit_per_user.unwrap_or(0)),
+            format!("{}s", new.rate_limit_per_user.unwrap_or(0)),
+        ));
+    }
+
+    if changes.is_empty() {
+        return Vec::new();
+    }
+
+    let mut msg = vec![text::Line::from(vec![
+        location,
+        Span::styled("The thread ", update_style),
+        Span::raw(new.name.clone()),
+        Span::styled(" was updated:", update_style),
+    ])];
+    msg.extend(changes);
+    msg
+}
+
+fn thread_delete(
+    thread: &PartialGuildChannel,
+    full_thread_data: Option<&GuildChannel>,
+    guild_name: &str,
+    names: &Names,
+) -> Vec<text::Line<'static>> {
+    let delete_style = Style::default().fg(Color::Red);
+    let location = Span::styled(
+        format!("[{}] ", guild_name),
+        Style::default().fg(Color::DarkGray),
+    );
+    let parent = channel_label(names, thread.parent_id);
+
+    let line = match full_thread_data {
+        Some(full_thread) => text::Line::from(vec![
+            location,
+            Span::styled("A thread ", delete_style),
+            Span::raw(full_thread.name.clone()),
+            Span::styled(format!(" in {parent} was deleted"), delete_style),
+        ]),
+        None => text::Line::from(vec![
+            location,
+            Span::styled(
+                format!(
+                    "A {} (ID:{}) in {parent} was deleted but was not in cache",
+                    thread.kind.name(),
+                    thread.id,
+                ),
+                delete_style,
+            ),
+        ]),
+    };
+
+    vec![line]
+}
+
+fn state_line(enabled: bool, on: &str, off: &str) -> text::Line<'static> {
+    let (state, color) = if enabled {
+        (on, Color::Red)
+    } else {
+        (off, Color::Green)
+    };
+    text::Line::from(vec![
+        Span::styled("  state: ", Style::default().fg(Color::Yellow)),
+        Span::styled(state.to_string(), Style::default().fg(color)),
+    ])
+}
+
+fn archive_duration(duration: AutoArchiveDuration) -> String {
+    match u16::from(duration) {
+        0 => String::from("never"),
+        minutes if minutes % 1440 == 0 => format!("{}d", minutes / 1440),
+        minutes if minutes % 60 == 0 => format!("{}h", minutes / 60),
+        minutes => format!("{minutes}m"),
+    }
+}
+
+/// Accounts younger than this are flagged when they join.
+const NEW_ACCOUNT_AGE_SECS: i64 = 7 * 24 * 60 * 60;
+
+fn guild_member_addition(
+    new_member: &Member,
+    guild_name: &str,
+    received: Timestamp,
+) -> Vec<text::Line<'static>> {
+    let age = received.unix_timestamp() - new_member.user.id.created_at().unix_timestamp();
+
+    let mut line = vec![
+        Span::styled(
+            format!("[{}] ", guild_name),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::raw(new_member.user.name.clone()),
+        Span::styled(
+            format!(
+                " ({}) joined, account age {}",
+                new_member.user.id,
+                format_age(age)
+            ),
+            Style::default().fg(Color::Green),
+        ),
+    ];
+    if age < NEW_ACCOUNT_AGE_SECS {
+        line.push(Span::styled(
+            " [NEW ACCOUNT]",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    vec![text::Line::from(line)]
+}
+
+fn guild_member_removal(user: &User, guild_name: &str) -> Vec<text::Line<'static>> {
+    vec![text::Line::from(vec![
+        Span::styled(
+            format!("[{}] ", guild_name),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::raw(user.name.clone()),
+        Span::styled(
+            format!(" ({}) left", user.id),
+            Style::default().fg(Color::Red),
+        ),
+    ])]
+}
+
+fn guild_member_update(
+    old_if_available: Option<&Member>,
+    new: Option<&Member>,
+    event: &GuildMemberUpdateEvent,
+    guild_name: &str,
+    received: Timestamp,
+    names: &Names,
+) -> Vec<text::Line<'static>> {
+    let update_style = Style::default().fg(Color::Yellow);
+    let header = |suffix: &'static str| {
+        text::Line::from(vec![
+            Span::styled(
+                format!("[{}] ", guild_name),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::raw(event.user.name.clone()),
+            Span::styled(suffix, update_style),
+        ])
+    };
+
+    let (Some(old), Some(new)) = (old_if_available, new) else {
+        return vec![header(
+            " was updated but the previous state was not in cache",
+        )];
+    };
+
+    let mut changes = Vec::new();
+    if old.nick != new.nick {
+        changes.push(diff_line(
+            "nickname",
+            old.nick.as_deref().unwrap_or("none"),
+            new.nick.as_deref().unwrap_or("none"),
+        ));
+    }
+
+    let added: Vec<String> = new
+        .roles
+        .iter()
+        .filter(|role| !old.roles.contains(role))
+        .map(|role| role_label(names, *role))
+        .collect();
+    if !added.is_empty() {
+        changes.push(text::Line::from(vec![
+            Span::styled("  roles added: ", update_style),
+            Span::styled(added.join(", "), Style::default().fg(Color::Green)),
+        ]));
+    }
+    let removed: Vec<String> = old
+        .roles
+        .iter()
+        .filter(|role| !new.roles.contains(role))
+        .map(|role| role_label(names, *role))
+        .collect();
+    if !removed.is_empty() {
+        changes.push(text::Line::from(vec![
+            Span::styled("  roles removed: ", update_style),
+            Span::styled(removed.join(", "), Style::default().fg(Color::Red)),
+        ]));
+    }
+
+    if old.communication_disabled_until != new.communication_disabled_until {
+        let now = received.unix_timestamp();
+        let line = match new.communication_disabled_until {
+            Some(until) if until.unix_timestamp() > now => text::Line::from(vec![
+                Span::styled("  timed out until ", update_style),
+                Span::styled(
+                    format!("{} ({})", until, format_age(until.unix_timestamp() - now)),
+                    Style::default().fg(Color::Red),
+                ),
+            ]),
+            _ => text::Line::from(vec![
+                Span::styled("  timeout: ", update_style),
+                Span::styled("removed", Style::default().fg(Color::Green)),
+            ]),
+        };
+        changes.push(line);
+    }
+
+    if old.avatar != new.avatar {
+        changes.push(text::Line::from(Span::styled(
+            "  guild avatar changed",
+            update_style,
+        )));
+    }
+
+    if changes.is_empty() {
+        return Vec::new();
+    }
+
+    let mut msg = vec![header(" was updated:")];
+    msg.extend(changes);
+    msg
+}
+
+fn format_age(secs: i64) -> String {
+    let secs = secs.max(0);
+    let (days, hours, minutes) = (secs / 86400, (secs / 3600) % 24, (secs / 60) % 60);
+    match days {
+        365.. => format!("{}y {}d", days / 365, days % 365),
+        1.. => format!("{days}d {hours}h"),
+        _ if hours > 0 => format!("{hours}h {minutes}m"),
+        _ => format!("{minutes}m"),
+    }
+}
+
+/// Reactions on the same message within this window of the first are collapsed.
+const REACTION_BURST_WINDOW: Duration = Duration::from_secs(10);
+
+/// Reactions on one message collapsed into a single line.
+struct ReactionBurst {
+    seq: u64,
+    message_id: MessageId,
+    started: Timestamp,
+    /// Each emoji with whether it was added or removed.
+    changes: Vec<(bool, String)>,
+    users: Vec<String>,
+}
+
+impl ReactionBurst {
+    fn line(&self, guild_name: &str, channel_name: &str) -> text::Line<'static> {
+        let reaction_style = Style::default().fg(Color::LightBlue);
+        let mut line = vec![
+            Span::styled(
+                format!("[{}] [#{}] ", guild_name, channel_name),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(
+                format!("reactions on message {}: ", self.message_id),
+                reaction_style,
+            ),
+        ];
+        for (added, emoji) in &self.changes {
+            let (sign, color) = if *added {
+                ("+", Color::Green)
+            } else {
+                ("-", Color::Red)
+            };
+            line.push(Span::styled(
+                format!("{sign}{emoji} "),
+                Style::default().fg(color),
+            ));
+        }
+        line.push(Span::styled("by ", reaction_style));
+        line.push(Span::raw(self.users.join(", ")));
+        text::Line::from(line)
+    }
+}
+
+/// The reaction, whether it was added, and the user, guild and channel names of a reaction event.
+fn reaction_parts(event: &StoredEvent) -> Option<(&Reaction, bool, &str, &str, &str)> {
+    let Payload::Known(event) = &event.payload else {
+        return None;
+    };
+    match event.as_ref() {
+        WebSocketEvent::ReactionAdd {
+            add_reaction,
+            user_name,
+            guild_name,
+            channel_name,
+        } => Some((add_reaction, true, user_name, guild_name, channel_name)),
+        WebSocketEvent::ReactionRemove {
+            removed_reaction,
+            user_name,
+            guild_name,
+            channel_name,
+        } => Some((removed_reaction, false, user_name, guild_name, channel_name)),
+        _ => None,
+    }
+}
+
+fn reaction_line(
+    reaction: &Reaction,
+    added: bool,
+    user_name: &str,
+    guild_name: &str,
+    channel_name: &str,
+    names: &Names,
+) -> text::Line<'static> {
+    let reaction_style = Style::default().fg(Color::LightBlue);
+    let action = if added { "reacted with" } else { "removed" };
+    let super_reaction = if reaction.burst { " (super)" } else { "" };
+    text::Line::from(vec![
+        Span::styled(
+            format!("[{}] [#{}] ", guild_name, channel_name),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::raw(user_name.to_string()),
+        Span::styled(format!(" {action} "), reaction_style),
+        Span::raw(format_emoji(&reaction.emoji, names)),
+        Span::styled(
+            format!("{super_reaction} on message {}", reaction.message_id),
+            reaction_style,
+        ),
+    ])
+}
+
+fn format_emoji(emoji: &ReactionType, names: &Names) -> String {
+    match emoji {
+        ReactionType::Unicode(emoji) => emoji.clone(),
+        ReactionType::Custom { animated, id, name } => {
+            let name = name
+                .clone()
+                .or_else(|| names.emoji(*id).map(String::from))
+                .unwrap_or_else(|| id.to_string());
+            if *animated {
+                format!(":{name}: (animated)")
+            } else {
+                format!(":{name}:")
+            }
+        }
+        _ => String::from("unknown emoji"),
+    }
+}
+
+fn voice_state_update(
+    old: Option<&VoiceState>,
+    new: &VoiceState,
+    guild_name: Option<&str>,
+    old_channel_name: Option<&str>,
+    new_channel_name: Option<&str>,
+    user_name: Option<&str>,
+) -> Vec<text::Line<'static>> {
+    let user_name = user_name.map_or_else(|| new.user_id.to_string(), String::from);
+
+    let transitions = voice::classify(old, new);
+    if transitions.is_empty() {
+        return Vec::new();
+    }
+
+    let channel = |name: Option<&str>, id: ChannelId| {
+        format!("#{}", name.map_or_else(|| id.to_string(), String::from))
+    };
+    let toggle = |enabled: bool, on: &str, off: &str| {
+        if enabled {
+            on.to_string()
+        } else {
+            off.to_string()
+        }
+    };
+    let descriptions: Vec<String> = transitions
+        .into_iter()
+        .map(|transition| match transition {
+            VoiceTransition::Join(id) => format!("joined {}", channel(new_channel_name, id)),
+            VoiceTransition::Leave(id) => format!("left {}", channel(old_channel_name, id)),
+            VoiceTransition::Move { from, to } => format!(
+                "moved from {} to {}",
+                channel(old_channel_name, from),
+                channel(new_channel_name, to)
+            ),
+            VoiceTransition::ServerMute(muted) => {
+                toggle(muted, "was server muted", "was server unmuted")
+            }
+            VoiceTransition::ServerDeafen(deafened) => {
+                toggle(deafened, "was server deafened", "was server undeafened")
+            }
+            VoiceTransition::SelfMute(muted) => toggle(muted, "muted", "unmuted"),
+            VoiceTransition::SelfDeafen(deafened) => toggle(deafened, "deafened", "undeafened"),
+            VoiceTransition::Stream(streaming) => {
+                toggle(streaming, "started streaming", "stopped streaming")
+            }
+            VoiceTransition::Video(video) => {
+                toggle(video, "turned their camera on", "turned their camera off")
+            }
+        })
+        .collect();
+
+    let guild_name = guild_name.unwrap_or("Unknown guild");
+    vec![text::Line::from(vec![
+        Span::styled(
+            format!("[{}] ", guild_name),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::raw(user_name),
+        Span::styled(
+            format!(" {}", descriptions.join(", ")),
+            Style::default().fg(Color::LightMagenta),
+        ),
+    ])]
+}
+
+fn guild_create(guild: &Guild, is_new: Option<bool>) -> Vec<text::Line<'static>> {
+    let (action, color) = if is_new == Some(true) {
+        ("Joined a new guild: ", Color::Green)
+    } else {
+        ("Guild available: ", Color::LightBlue)
+    };
+    vec![text::Line::from(vec![
+        Span::styled(action, Style::default().fg(color)),
+        Span::raw(guild.name.clone()),
+        Span::styled(
+            format!(
+                " ({} members, {} channels, {} roles, {} emojis)",
+                guild.member_count,
+                guild.channels.len(),
+                guild.roles.len(),
+                guild.emojis.len()
+            ),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ])]
+}
+
+/// How long the one line summary of an unknown event can get.
+const UNKNOWN_SUMMARY_LEN: usize = 160;
+
+/// Renders any event without a dedicated handler from its tag and raw data, or one with
+/// a handler whose data didn't parse, along with the `error`.
+///
+/// Typed variants without a handler yet can be stored as [`Payload::Unknown`] with
+/// `serde_json::to_value`.
+fn unknown_event(
+    variant: &str,
+    data: &serde_json::Value,
+    error: Option<&str>,
+    expand: bool,
+) -> Vec<text::Line<'static>> {
+    let unknown_style = Style::default().fg(Color::DarkGray);
+    let header = match error {
+        Some(_) => Span::styled("[failed to parse] ", Style::default().fg(Color::Red)),
+        None => Span::styled("[unknown event] ", unknown_style),
+    };
+    let variant = Span::styled(variant.to_string(), Style::default().fg(Color::Yellow));
+    let error =
+        error.map(|error| Span::styled(format!(" ({error})"), Style::default().fg(Color::Red)));
+
+    if expand {
+        let mut first = vec![header, variant];
+        first.extend(error);
+        let mut msg = vec![text::Line::from(first)];
+        // serializing a `Value` can't fail.
+        let pretty = serde_json::to_string_pretty(data).unwrap_or_default();
+        for line in pretty.lines() {
+            msg.push(text::Line::from(Span::styled(
+                format!("  {line}"),
+                unknown_style,
+            )));
+        }
+        msg
+    } else {
+        let mut summary = summarize_json(data);
+        if summary.chars().count() > UNKNOWN_SUMMARY_LEN {
+            summary = summary.chars().take(UNKNOWN_SUMMARY_LEN).collect();
+            summary.push('…');
+        }
+        let mut line = vec![header, variant];
+        line.extend(error);
+        line.push(Span::raw(format!(" {summary}")));
+        vec![text::Line::from(line)]
+    }
+}
+
+/// A compact view of the top level of `value`, nested values are only hinted at.
+fn summarize_json(value: &serde_json::Value) -> String {
+    use serde_json::Value;
+
+    let short = |value: &Value| match value {
+        Value::Object(object) if object.is_empty() => String::from("{}"),
+        Value::Object(_) => String::from("{…}"),
+        Value::Array(array) => format!("[{}]", array.len()),
+        other => other.to_string(),
+    };
+
+    match value {
+        Value::Object(object) => {
+            let fields: Vec<String> = object
+                .iter()
+                .map(|(key, value)| format!("{key}: {}", short(value)))
+                .collect();
+            format!("{{ {} }}", fields.join(", "))
+        }
+        other => short(other),
+    }
 }
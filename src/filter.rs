@@ -0,0 +1,315 @@
+use std::fmt;
+
+use regex::Regex;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::store::StoredEvent;
+
+/// A named set of rules, saved in the config.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    pub name: String,
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Include,
+    Exclude,
+}
+
+/// What a rule looks at, IDs are kept as numbers so they're easy to write by hand.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Condition {
+    Guild(u64),
+    Channel(u64),
+    User(u64),
+    /// The event's tag, compared ignoring case.
+    Kind(String),
+    Bot(bool),
+    /// A regex over the content of the message the event is about.
+    Content(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub action: Action,
+    pub condition: Condition,
+}
+
+/// Rules are written as `[+|-]key:value`, e.g. `-kind:ReactionAdd` or `+content:"hello there"`.
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match self.action {
+            Action::Include => '+',
+            Action::Exclude => '-',
+        };
+        match &self.condition {
+            Condition::Guild(id) => write!(f, "{sign}guild:{id}"),
+            Condition::Channel(id) => write!(f, "{sign}channel:{id}"),
+            Condition::User(id) => write!(f, "{sign}user:{id}"),
+            Condition::Kind(kind) => write!(f, "{sign}kind:{kind}"),
+            Condition::Bot(bot) => write!(f, "{sign}bot:{}", if *bot { "yes" } else { "no" }),
+            Condition::Content(pattern) => write!(f, "{sign}content:\"{}\"", escape(pattern)),
+        }
+    }
+}
+
+/// Escapes `pattern` for quoting, only where [`tokenize`] would otherwise read it wrong so
+/// a regex like `\d+` stays as it was written.
+fn escape(pattern: &str) -> String {
+    let mut escaped = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' if matches!(chars.peek(), None | Some('"' | '\\')) => escaped.push_str("\\\\"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats `rules` the way [`parse_rules`] reads them.
+pub fn format_rules(rules: &[Rule]) -> String {
+    rules
+        .iter()
+        .map(Rule::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses whitespace separated rules, a value with spaces can be wrapped in double quotes.
+pub fn parse_rules(input: &str) -> Result<Vec<Rule>, String> {
+    tokenize(input)?
+        .iter()
+        .map(|token| parse_rule(token))
+        .collect()
+}
+
+fn tokenize(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(tokens);
+        }
+
+        let mut token = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            if c != '"' {
+                token.push(c);
+                continue;
+            }
+            // only `\"` and `\\` are escapes inside quotes, regexes need their backslashes.
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') if matches!(chars.peek(), Some('"' | '\\')) => {
+                        token.push(chars.next().unwrap());
+                    }
+                    Some(c) => token.push(c),
+                    None => return Err(format!("unclosed quote in `{token}`")),
+                }
+            }
+        }
+        tokens.push(token);
+    }
+}
+
+fn parse_rule(token: &str) -> Result<Rule, String> {
+    let (action, rest) = match token.chars().next() {
+        Some('+') => (Action::Include, &token[1..]),
+        Some('-' | '!') => (Action::Exclude, &token[1..]),
+        _ => (Action::Include, token),
+    };
+    let (key, value) = rest.split_once(':').unwrap_or((rest, ""));
+
+    let id = |value: &str| match value.parse::<u64>() {
+        Ok(id) if id != 0 => Ok(id),
+        _ => Err(format!("`{token}` needs an ID, e.g. {key}:1234")),
+    };
+    let condition = match key {
+        "guild" => Condition::Guild(id(value)?),
+        "channel" => Condition::Channel(id(value)?),
+        "user" => Condition::User(id(value)?),
+        "kind" if !value.is_empty() => Condition::Kind(value.to_string()),
+        "kind" => {
+            return Err(format!(
+                "`{token}` needs an event kind, e.g. kind:NewMessage"
+            ))
+        }
+        "bot" => match value {
+            "" | "yes" | "true" => Condition::Bot(true),
+            "no" | "false" => Condition::Bot(false),
+            _ => return Err(format!("`{token}` should be bot:yes or bot:no")),
+        },
+        "content" => {
+            Regex::new(value).map_err(|err| format!("invalid regex in `{token}`: {err}"))?;
+            Condition::Content(value.to_string())
+        }
+        _ => {
+            return Err(format!(
+                "unknown rule `{token}`, expected guild, channel, user, kind, bot or content"
+            ))
+        }
+    };
+    Ok(Rule { action, condition })
+}
+
+/// A filter ready to match events, with its regexes compiled.
+///
+/// An event is shown when it matches any include rule, or there are none, and no
+/// exclude rule.
+#[derive(Debug)]
+pub struct ActiveFilter {
+    /// The saved filter it came from, empty if it hasn't been saved.
+    pub name: String,
+    pub rules: Vec<Rule>,
+    includes: Vec<Matcher>,
+    excludes: Vec<Matcher>,
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Guild(u64),
+    Channel(u64),
+    User(u64),
+    Kind(String),
+    Bot(bool),
+    Content(Regex),
+}
+
+impl ActiveFilter {
+    pub fn new(name: String, rules: &[Rule]) -> Result<ActiveFilter, regex::Error> {
+        let mut filter = ActiveFilter {
+            name,
+            rules: rules.to_vec(),
+            includes: Vec::new(),
+            excludes: Vec::new(),
+        };
+        for rule in rules {
+            let matcher = match &rule.condition {
+                Condition::Guild(id) => Matcher::Guild(*id),
+                Condition::Channel(id) => Matcher::Channel(*id),
+                Condition::User(id) => Matcher::User(*id),
+                Condition::Kind(kind) => Matcher::Kind(kind.clone()),
+                Condition::Bot(bot) => Matcher::Bot(*bot),
+                Condition::Content(pattern) => Matcher::Content(Regex::new(pattern)?),
+            };
+            match rule.action {
+                Action::Include => filter.includes.push(matcher),
+                Action::Exclude => filter.excludes.push(matcher),
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, event: &StoredEvent) -> bool {
+        (self.includes.is_empty() || self.includes.iter().any(|m| m.matches(event)))
+            && !self.excludes.iter().any(|m| m.matches(event))
+    }
+}
+
+impl Matcher {
+    fn matches(&self, event: &StoredEvent) -> bool {
+        match self {
+            Matcher::Guild(id) => event.guild_id.is_some_and(|guild_id| guild_id.get() == *id),
+            Matcher::Channel(id) => event
+                .channel_id
+                .is_some_and(|channel_id| channel_id.get() == *id),
+            Matcher::User(id) => event.user_id.is_some_and(|user_id| user_id.get() == *id),
+            Matcher::Kind(kind) => event.kind().eq_ignore_ascii_case(kind),
+            Matcher::Bot(bot) => event.is_bot() == Some(*bot),
+            Matcher::Content(regex) => event
+                .content()
+                .is_some_and(|content| regex.is_match(content)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(action: Action, condition: Condition) -> Rule {
+        Rule { action, condition }
+    }
+
+    #[test]
+    fn tokenize_splits_on_whitespace_outside_quotes() {
+        assert_eq!(
+            tokenize("  -kind:ReactionAdd\t+content:\"hello there\"  bot ").unwrap(),
+            ["-kind:ReactionAdd", "+content:hello there", "bot"]
+        );
+        assert_eq!(tokenize("").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn tokenize_escapes_inside_quotes() {
+        assert_eq!(tokenize(r#""say \"hi\"""#).unwrap(), [r#"say "hi""#]);
+        assert_eq!(tokenize(r#""a\\b""#).unwrap(), [r"a\b"]);
+        // any other backslash is part of the regex.
+        assert_eq!(tokenize(r#""\d+\s""#).unwrap(), [r"\d+\s"]);
+    }
+
+    #[test]
+    fn parses_every_condition() {
+        assert_eq!(
+            parse_rules("guild:1 -channel:2 !user:3 +kind:NewMessage bot -bot:no content:hi")
+                .unwrap(),
+            [
+                rule(Action::Include, Condition::Guild(1)),
+                rule(Action::Exclude, Condition::Channel(2)),
+                rule(Action::Exclude, Condition::User(3)),
+                rule(Action::Include, Condition::Kind("NewMessage".into())),
+                rule(Action::Include, Condition::Bot(true)),
+                rule(Action::Exclude, Condition::Bot(false)),
+                rule(Action::Include, Condition::Content("hi".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn formatted_rules_parse_back() {
+        let rules = vec![
+            rule(Action::Include, Condition::Guild(1234)),
+            rule(Action::Exclude, Condition::Kind("ReactionAdd".into())),
+            rule(Action::Exclude, Condition::Bot(true)),
+            rule(Action::Include, Condition::Content("hello there".into())),
+            rule(Action::Include, Condition::Content(r#"say "hi""#.into())),
+            rule(Action::Include, Condition::Content(r"\d+\s\\".into())),
+            rule(Action::Include, Condition::Content(r#"\\\""#.into())),
+        ];
+        assert_eq!(parse_rules(&format_rules(&rules)).unwrap(), rules);
+    }
+
+    #[test]
+    fn regex_ending_in_a_backslash_round_trips() {
+        let rules = vec![rule(Action::Include, Condition::Content(r"C:\\".into()))];
+        assert_eq!(format_rules(&rules), r#"+content:"C:\\\\""#);
+        assert_eq!(parse_rules(&format_rules(&rules)).unwrap(), rules);
+    }
+
+    #[test]
+    fn plain_regexes_are_written_as_they_are() {
+        let rules = vec![rule(Action::Include, Condition::Content(r"\bhi\b".into()))];
+        assert_eq!(format_rules(&rules), r#"+content:"\bhi\b""#);
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        let error = |input: &str| parse_rules(input).unwrap_err();
+        assert!(error("content:\"open").starts_with("unclosed quote"));
+        assert!(error("colour:red").starts_with("unknown rule"));
+        assert!(error("guild:abc").contains("needs an ID"));
+        assert!(error("user:0").contains("needs an ID"));
+        assert!(error("channel").contains("needs an ID"));
+        assert!(error("kind:").contains("needs an event kind"));
+        assert!(error("bot:maybe").contains("bot:yes or bot:no"));
+        assert!(error("content:(").starts_with("invalid regex"));
+    }
+}
@@ -1,42 +1,85 @@
 pub mod app;
+mod cache;
+pub mod cli;
+pub mod config;
+pub mod connection;
 pub mod crossterm;
+pub mod diagnostics;
+pub mod dialog;
 pub mod event;
 mod event_handlers;
+pub mod filter;
+pub mod search;
+pub mod store;
+pub mod theme;
 pub mod ui;
+pub mod voice;
+pub mod watchlist;
+pub mod wrap;
 
-use futures_util::StreamExt;
-use std::{error::Error, sync::mpsc, time::Duration};
-use tokio_tungstenite::{connect_async, WebSocketStream};
+use std::{error::Error, time::Duration};
+
+use tokio::sync::mpsc;
+
+use crate::{cli::Args, config::Config, connection::ConnectionCommand, crossterm::Startup};
+
+/// How long the connection gets to close cleanly after the interface has stopped.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let (ws_stream, _) = connect_async("ws://192.168.0.113:8080")
-        .await
-        .expect("Failed to connect to WebSocket server");
-
-    let (sender, receiver) = mpsc::channel();
-    let ws_sender = sender.clone();
-    tokio::spawn(async move {
-        handle_websocket_events(ws_stream, ws_sender).await;
+    let args: Args = argh::from_env();
+
+    let config_path = args.config.clone().unwrap_or_else(Config::default_path);
+    // a config we failed to read is never written back, so it can't be clobbered.
+    let (config, config_path, config_error) = match Config::load(&config_path) {
+        Ok(config) => (config, Some(config_path), None),
+        Err(err) => (
+            Config::default(),
+            None,
+            Some(format!("{}: {err}", config_path.display())),
+        ),
+    };
+
+    let url = args.url(config.startup_server());
+
+    let (frame_sender, frame_receiver) = mpsc::unbounded_channel();
+    let (status_sender, status_receiver) = mpsc::unbounded_channel();
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let max_retries = args.max_retries;
+    let ws_url = url.clone();
+    let shutdown = command_sender.clone();
+    let supervisor = tokio::spawn(async move {
+        connection::supervise(
+            ws_url,
+            max_retries,
+            frame_sender,
+            status_sender,
+            command_receiver,
+        )
+        .await;
     });
 
-    let tick_rate = Duration::from_millis(50);
-    crate::crossterm::run(tick_rate, receiver)?;
-    Ok(())
-}
+    let startup = Startup {
+        url,
+        config,
+        config_path,
+        config_error,
+    };
+    crate::crossterm::run(
+        &args,
+        startup,
+        frame_receiver,
+        status_receiver,
+        command_sender,
+    )
+    .await?;
 
-async fn handle_websocket_events(
-    mut ws_stream: WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>,
-    ws_sender: mpsc::Sender<String>,
-) {
-    while let Some(message) = ws_stream.next().await {
-        match message {
-            Ok(msg) => {
-                let _ = ws_sender.send(msg.to_string());
-            }
-            Err(_err) => {
-                // Handle error in the future dumbass.
-            }
-        }
+    // the interface is gone, send a close frame before exiting.
+    let _ = shutdown.send(ConnectionCommand::Shutdown);
+    // the panic message went to the alternate screen, show it again now it's gone.
+    if let Ok(Err(err)) = tokio::time::timeout(SHUTDOWN_TIMEOUT, supervisor).await {
+        eprintln!("the connection supervisor failed: {err}");
     }
+    Ok(())
 }
@@ -0,0 +1,148 @@
+use std::{fmt, ops::Range};
+
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+};
+use regex::{Regex, RegexBuilder};
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    Plain,
+    #[default]
+    IgnoreCase,
+    Regex,
+}
+
+impl SearchMode {
+    pub fn next(self) -> SearchMode {
+        match self {
+            SearchMode::Plain => SearchMode::IgnoreCase,
+            SearchMode::IgnoreCase => SearchMode::Regex,
+            SearchMode::Regex => SearchMode::Plain,
+        }
+    }
+}
+
+impl fmt::Display for SearchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SearchMode::Plain => "plain",
+            SearchMode::IgnoreCase => "ignore case",
+            SearchMode::Regex => "regex",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A search over the wrapped lines of the events view.
+pub struct Search {
+    pub query: String,
+    pub mode: SearchMode,
+    /// Whether keys go to the query, stopped by Enter.
+    pub editing: bool,
+    pub error: Option<String>,
+    /// The event and which of its wrapped lines the current match is on.
+    pub current: Option<(u64, usize)>,
+    /// The wrapped line the search started from, where typing looks for the first match.
+    pub anchor: usize,
+    regex: Option<Regex>,
+}
+
+impl Search {
+    pub fn new(mode: SearchMode, anchor: usize) -> Search {
+        Search {
+            query: String::new(),
+            mode,
+            editing: true,
+            error: None,
+            current: None,
+            anchor,
+            regex: None,
+        }
+    }
+
+    /// The compiled query, `None` while it's empty or invalid.
+    pub fn regex(&self) -> Option<&Regex> {
+        self.regex.as_ref()
+    }
+
+    /// Compiles the query again after it or the mode changed.
+    pub fn update(&mut self) {
+        self.regex = None;
+        self.error = None;
+        if self.query.is_empty() {
+            return;
+        }
+
+        let pattern = match self.mode {
+            SearchMode::Plain | SearchMode::IgnoreCase => regex::escape(&self.query),
+            SearchMode::Regex => self.query.clone(),
+        };
+        match RegexBuilder::new(&pattern)
+            .case_insensitive(self.mode == SearchMode::IgnoreCase)
+            .build()
+        {
+            Ok(regex) => self.regex = Some(regex),
+            Err(err) => self.error = Some(err.to_string()),
+        }
+    }
+}
+
+pub fn line_text(line: &Line<'_>) -> String {
+    line.spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect()
+}
+
+/// Patches `style` onto every part of `line` matching `regex`, splitting spans where a
+/// match starts or ends inside one.
+pub fn highlight(line: Line<'static>, regex: &Regex, style: Style) -> Line<'static> {
+    let text = line_text(&line);
+    let matches: Vec<Range<usize>> = regex
+        .find_iter(&text)
+        .filter(|m| !m.is_empty())
+        .map(|m| m.range())
+        .collect();
+    if matches.is_empty() {
+        return line;
+    }
+
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for span in line.spans {
+        let content = span.content.as_ref();
+        let end = offset + content.len();
+
+        // the boundaries of the matches inside this span, relative to its start.
+        let mut cuts = vec![0, content.len()];
+        for m in matches.iter().filter(|m| m.start < end && m.end > offset) {
+            cuts.push(m.start.saturating_sub(offset));
+            cuts.push(m.end.min(end) - offset);
+        }
+        cuts.sort_unstable();
+        cuts.dedup();
+
+        for part in cuts.windows(2) {
+            let (start, stop) = (part[0], part[1]);
+            let matched = matches
+                .iter()
+                .any(|m| m.start <= offset + start && offset + stop <= m.end);
+            let part_style = if matched {
+                span.style.patch(style)
+            } else {
+                span.style
+            };
+            spans.push(Span::styled(content[start..stop].to_string(), part_style));
+        }
+        offset = end;
+    }
+
+    Line {
+        spans,
+        alignment: line.alignment,
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+
+use serenity::all::{ChannelId, GuildId, Timestamp, UserId};
+
+use crate::{cache::Names, event::WebSocketEvent};
+
+/// What was received, either an event we have types for or just its tag and JSON.
+#[derive(Debug)]
+pub enum Payload {
+    Known(Box<WebSocketEvent>),
+    Unknown {
+        variant: String,
+        data: serde_json::Value,
+        /// Why it didn't parse, when the tag is one we do have types for.
+        error: Option<String>,
+    },
+}
+
+/// An event as it was received, rendered only when it's drawn.
+#[derive(Debug)]
+pub struct StoredEvent {
+    /// Assigned by the [`EventStore`], unique and increasing for the life of the client.
+    pub seq: u64,
+    pub received: Timestamp,
+    pub guild_id: Option<GuildId>,
+    pub channel_id: Option<ChannelId>,
+    pub user_id: Option<UserId>,
+    pub payload: Payload,
+    /// The names it refers to, as they were when it arrived.
+    pub names: Names,
+    /// Whether it matched the watchlist when it arrived.
+    pub watched: bool,
+    /// The length of the frame it came from, an estimate of how much memory it takes.
+    pub size: usize,
+}
+
+impl StoredEvent {
+    /// Wraps `payload` as received now, its sequence number is set once it's stored.
+    pub fn new(payload: Payload, names: Names, size: usize) -> StoredEvent {
+        let (guild_id, channel_id, user_id) = match &payload {
+            Payload::Known(event) => event_ids(event),
+            Payload::Unknown { .. } => (None, None, None),
+        };
+        StoredEvent {
+            seq: 0,
+            received: Timestamp::now(),
+            guild_id,
+            channel_id,
+            user_id,
+            payload,
+            names,
+            watched: false,
+            size,
+        }
+    }
+
+    /// The event's tag, e.g. `NewMessage`.
+    pub fn kind(&self) -> &str {
+        match &self.payload {
+            Payload::Known(event) => event_kind(event),
+            Payload::Unknown { variant, .. } => variant,
+        }
+    }
+
+    /// The text of the message the event is about, if it's about one.
+    pub fn content(&self) -> Option<&str> {
+        let Payload::Known(event) = &self.payload else {
+            return None;
+        };
+        match &**event {
+            WebSocketEvent::NewMessage { message, .. } => Some(&message.content),
+            WebSocketEvent::MessageEdit { new, event, .. } => new
+                .as_ref()
+                .map(|message| message.content.as_str())
+                .or(event.content.as_deref()),
+            WebSocketEvent::MessageDelete { message, .. } => {
+                message.as_ref().map(|message| message.content.as_str())
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the user the event is about is a bot, `None` when we can't tell.
+    pub fn is_bot(&self) -> Option<bool> {
+        let Payload::Known(event) = &self.payload else {
+            return None;
+        };
+        match &**event {
+            WebSocketEvent::NewMessage { message, .. } => Some(message.author.bot),
+            WebSocketEvent::MessageEdit { new, event, .. } => new
+                .as_ref()
+                .map(|message| message.author.bot)
+                .or_else(|| event.author.as_ref().map(|author| author.bot)),
+            WebSocketEvent::MessageDelete { message, .. } => {
+                message.as_ref().map(|message| message.author.bot)
+            }
+            WebSocketEvent::GuildMemberAddition { new_member, .. } => Some(new_member.user.bot),
+            WebSocketEvent::GuildMemberRemoval { user, .. } => Some(user.bot),
+            WebSocketEvent::GuildMemberUpdate { event, .. } => Some(event.user.bot),
+            WebSocketEvent::ReactionAdd {
+                add_reaction: reaction,
+                ..
+            }
+            | WebSocketEvent::ReactionRemove {
+                removed_reaction: reaction,
+                ..
+            } => reaction.member.as_ref().map(|member| member.user.bot),
+            WebSocketEvent::VoiceStateUpdate { new, .. } => {
+                new.member.as_ref().map(|member| member.user.bot)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn event_kind(event: &WebSocketEvent) -> &'static str {
+    match event {
+        WebSocketEvent::NewMessage { .. } => "NewMessage",
+        WebSocketEvent::MessageEdit { .. } => "MessageEdit",
+        WebSocketEvent::MessageDelete { .. } => "MessageDelete",
+        WebSocketEvent::ChannelCreate { .. } => "ChannelCreate",
+        WebSocketEvent::ChannelUpdate { .. } => "ChannelUpdate",
+        WebSocketEvent::ChannelDelete { .. } => "ChannelDelete",
+        WebSocketEvent::ThreadCreate { .. } => "ThreadCreate",
+        WebSocketEvent::ThreadUpdate { .. } => "ThreadUpdate",
+        WebSocketEvent::ThreadDelete { .. } => "ThreadDelete",
+        WebSocketEvent::GuildCreate { .. } => "GuildCreate",
+        WebSocketEvent::GuildMemberAddition { .. } => "GuildMemberAddition",
+        WebSocketEvent::GuildMemberRemoval { .. } => "GuildMemberRemoval",
+        WebSocketEvent::GuildMemberUpdate { .. } => "GuildMemberUpdate",
+        WebSocketEvent::ReactionAdd { .. } => "ReactionAdd",
+        WebSocketEvent::ReactionRemove { .. } => "ReactionRemove",
+        WebSocketEvent::VoiceStateUpdate { .. } => "VoiceStateUpdate",
+    }
+}
+
+/// The guild, channel and user an event is about, where it has them.
+pub fn event_ids(event: &WebSocketEvent) -> (Option<GuildId>, Option<ChannelId>, Option<UserId>) {
+    match event {
+        WebSocketEvent::NewMessage { message, .. } => (
+            message.guild_id,
+            Some(message.channel_id),
+            Some(message.author.id),
+        ),
+        WebSocketEvent::MessageEdit { new, event, .. } => (
+            event.guild_id,
+            Some(event.channel_id),
+            new.as_ref()
+                .map(|message| message.author.id)
+                .or_else(|| event.author.as_ref().map(|author| author.id)),
+        ),
+        WebSocketEvent::MessageDelete {
+            channel_id,
+            guild_id,
+            message,
+            ..
+        } => (
+            *guild_id,
+            Some(*channel_id),
+            message.as_ref().map(|message| message.author.id),
+        ),
+        WebSocketEvent::ChannelCreate { channel, .. }
+        | WebSocketEvent::ChannelDelete { channel, .. }
+        | WebSocketEvent::ChannelUpdate { new: channel, .. }
+        | WebSocketEvent::ThreadCreate {
+            thread: channel, ..
+        }
+        | WebSocketEvent::ThreadUpdate { new: channel, .. } => {
+            (Some(channel.guild_id), Some(channel.id), None)
+        }
+        WebSocketEvent::ThreadDelete { thread, .. } => {
+            (Some(thread.guild_id), Some(thread.id), None)
+        }
+        WebSocketEvent::GuildCreate { guild, .. } => (Some(guild.id), None, None),
+        WebSocketEvent::GuildMemberAddition { new_member, .. } => {
+            (Some(new_member.guild_id), None, Some(new_member.user.id))
+        }
+        WebSocketEvent::GuildMemberRemoval { guild_id, user, .. } => {
+            (Some(*guild_id), None, Some(user.id))
+        }
+        WebSocketEvent::GuildMemberUpdate { event, .. } => {
+            (Some(event.guild_id), None, Some(event.user.id))
+        }
+        WebSocketEvent::ReactionAdd {
+            add_reaction: reaction,
+            ..
+        }
+        | WebSocketEvent::ReactionRemove {
+            removed_reaction: reaction,
+            ..
+        } => (
+            reaction.guild_id,
+            Some(reaction.channel_id),
+            reaction.user_id,
+        ),
+        WebSocketEvent::VoiceStateUpdate { old, new, .. } => (
+            new.guild_id,
+            new.channel_id
+                .or_else(|| old.as_ref().and_then(|old| old.channel_id)),
+            Some(new.user_id),
+        ),
+    }
+}
+
+/// The events we're keeping, oldest first, dropping the oldest once it's full.
+#[derive(Debug)]
+pub struct EventStore {
+    pub events: VecDeque<StoredEvent>,
+    /// The most events kept at once.
+    pub capacity: usize,
+    /// The most bytes of events kept at once, going by [`StoredEvent::size`].
+    pub memory_budget: Option<usize>,
+    /// How many events have been dropped to stay within the limits.
+    pub evicted: u64,
+    bytes: usize,
+    next_seq: u64,
+}
+
+impl EventStore {
+    pub fn new(capacity: usize, memory_budget: Option<usize>) -> EventStore {
+        EventStore {
+            events: VecDeque::with_capacity(capacity.min(4096)),
+            capacity,
+            memory_budget,
+            evicted: 0,
+            bytes: 0,
+            next_seq: 0,
+        }
+    }
+
+    pub fn push(&mut self, mut event: StoredEvent) {
+        event.seq = self.next_seq;
+        self.next_seq += 1;
+        self.bytes += event.size;
+        self.events.push_back(event);
+
+        // the newest event is always kept, even if it's over the budget on its own.
+        while self.events.len() > self.capacity.max(1) || self.over_budget() {
+            let Some(oldest) = self.events.pop_front() else {
+                break;
+            };
+            self.bytes -= oldest.size;
+            self.evicted += 1;
+        }
+    }
+
+    /// The stored events from `seq` on, oldest first.
+    pub fn since(&self, seq: u64) -> impl Iterator<Item = &StoredEvent> {
+        let start = self.events.partition_point(|event| event.seq < seq);
+        self.events.range(start..)
+    }
+
+    pub fn first_seq(&self) -> Option<u64> {
+        self.events.front().map(|event| event.seq)
+    }
+
+    fn over_budget(&self) -> bool {
+        self.events.len() > 1 && self.memory_budget.is_some_and(|budget| self.bytes > budget)
+    }
+}
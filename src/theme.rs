@@ -0,0 +1,61 @@
+use ratatui::style::Color;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    #[default]
+    Default,
+    Light,
+    Monochrome,
+}
+
+/// Colours used for the interface around the events.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub title: Color,
+    pub tab: Color,
+    pub highlight: Color,
+    pub muted: Color,
+    pub status_bg: Color,
+    pub status_fg: Color,
+}
+
+impl ThemeName {
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemeName::Default => Theme {
+                title: Color::Magenta,
+                tab: Color::Green,
+                highlight: Color::Yellow,
+                muted: Color::DarkGray,
+                status_bg: Color::Black,
+                status_fg: Color::White,
+            },
+            ThemeName::Light => Theme {
+                title: Color::Blue,
+                tab: Color::Black,
+                highlight: Color::Magenta,
+                muted: Color::Gray,
+                status_bg: Color::White,
+                status_fg: Color::Black,
+            },
+            ThemeName::Monochrome => Theme {
+                title: Color::White,
+                tab: Color::Gray,
+                highlight: Color::White,
+                muted: Color::DarkGray,
+                status_bg: Color::Reset,
+                status_fg: Color::Reset,
+            },
+        }
+    }
+
+    pub fn next(self) -> ThemeName {
+        match self {
+            ThemeName::Default => ThemeName::Light,
+            ThemeName::Light => ThemeName::Monochrome,
+            ThemeName::Monochrome => ThemeName::Default,
+        }
+    }
+}
@@ -1,137 +1,837 @@
 use ratatui::{prelude::*, widgets::*};
 
-use crate::app::{App, MESSAGES};
+use crate::{
+    app::App, connection::ConnectionState, dialog::FilterField, event_handlers, filter, search,
+    store::Payload,
+};
 
 pub fn draw(f: &mut Frame, app: &mut App) {
+    let theme = app.config.theme.theme();
+    let search_height = u16::from(app.search.is_some());
+    let outer = Layout::default()
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(search_height),
+            Constraint::Length(1),
+        ])
+        .split(f.size());
+    let main_area = outer[0];
+    draw_status_bar(f, app, outer[2]);
+
     // a lot of the customisation code is bad, but will improve.
     let chunks = Layout::default()
         .constraints([Constraint::Length(3), Constraint::Min(0)])
-        .split(f.size());
+        .split(main_area);
     let titles = app
         .tabs
         .titles
         .iter()
-        .map(|t| text::Line::from(Span::styled(*t, Style::default().fg(Color::Green))))
+        .map(|t| text::Line::from(Span::styled(*t, Style::default().fg(theme.tab))))
         .collect();
 
+    app.tabs_area = app.show_tabs.then_some(chunks[0]);
     if app.show_tabs {
         let tabs = Tabs::new(titles)
             .block(Block::default().borders(Borders::ALL).title(app.title))
-            .highlight_style(Style::default().fg(Color::Yellow))
+            .highlight_style(Style::default().fg(theme.highlight))
             .select(app.tabs.index);
         f.render_widget(tabs, chunks[0]);
     }
 
-    // so it shuts up for now.
-    // improve this code later.
-    #[allow(clippy::single_match)]
+    let area = if app.show_tabs { chunks[1] } else { main_area };
     match app.tabs.index {
-        0 => {
-            if app.show_tabs {
-                draw_first_tab(f, app, chunks[1]);
-            } else {
-                draw_first_tab(f, app, f.size());
+        0 => draw_first_tab(f, app, area),
+        1 => draw_voice_tab(f, app, area),
+        2 => draw_diagnostics_tab(f, app, area),
+        3 => draw_alerts_tab(f, app, area),
+        _ => {}
+    }
+
+    // after the events, so the match count is up to date.
+    if app.search.is_some() {
+        draw_search_bar(f, app, outer[1]);
+    }
+    if app.event_detail.is_some() {
+        draw_event_detail(f, app);
+    }
+    if app.filter_editor.is_some() {
+        draw_filter_editor(f, app);
+    }
+    if app.connect_dialog.is_some() {
+        draw_connect_dialog(f, app);
+    }
+}
+
+fn draw_event_detail(f: &mut Frame, app: &mut App) {
+    let Some(detail) = &app.event_detail else {
+        return;
+    };
+    let theme = app.config.theme.theme();
+    let label = Style::default().fg(theme.muted);
+
+    let mut text: Vec<Line> = Vec::new();
+    let events = &app.events.events;
+    match events.binary_search_by_key(&detail.seq, |event| event.seq) {
+        Ok(index) => {
+            let event = &events[index];
+            let named = |id: Option<String>, name: Option<&str>| match (id, name) {
+                (Some(id), Some(name)) => format!("{name} ({id})"),
+                (Some(id), None) => id,
+                (None, _) => String::from("none"),
+            };
+
+            let guild = named(
+                event.guild_id.map(|id| id.to_string()),
+                event.names.guild.as_deref(),
+            );
+            let channel = named(
+                event.channel_id.map(|id| id.to_string()),
+                event.channel_id.and_then(|id| event.names.channel(id)),
+            );
+            let user = event
+                .user_id
+                .map_or(String::from("none"), |id| id.to_string());
+
+            for (name, value) in [
+                ("received", event.received.to_string()),
+                ("guild", guild),
+                ("channel", channel),
+                ("user", user),
+            ] {
+                text.push(Line::from(vec![
+                    Span::styled(format!("{name}: "), label),
+                    Span::raw(value),
+                ]));
+            }
+            text.push(Line::default());
+            text.extend(event_handlers::render_event(event, &app.config));
+            text.push(Line::default());
+
+            let json = match &event.payload {
+                Payload::Known(event) => serde_json::to_string_pretty(event),
+                Payload::Unknown { variant, data, .. } => {
+                    serde_json::to_string_pretty(&serde_json::json!({ variant: data }))
+                }
+            };
+            let json = json.unwrap_or_else(|err| format!("couldn't serialize the event: {err}"));
+            for line in json.lines() {
+                text.push(Line::from(Span::styled(line.to_string(), label)));
             }
         }
-        _ => {}
+        Err(_) => text.push(Line::from(Span::styled(
+            "This event is no longer in the history.",
+            label,
+        ))),
     }
+
+    let area = centered_rect(80, 80, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled(
+            format!("Event #{}", detail.seq),
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .title(
+            block::Title::from(Span::styled(
+                " Up/Down scroll | Esc close ",
+                Style::default().fg(theme.muted),
+            ))
+            .position(block::Position::Bottom),
+        );
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((detail.scroll, 0));
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn draw_connect_dialog(f: &mut Frame, app: &App) {
+    let Some(connect_dialog) = &app.connect_dialog else {
+        return;
+    };
+    let theme = app.config.theme.theme();
+
+    let area = centered_rect(60, 50, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled(
+            "Connect",
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .title(
+            block::Title::from(Span::styled(
+                " Enter connect | Up/Down recent | Esc close ",
+                Style::default().fg(theme.muted),
+            ))
+            .position(block::Position::Bottom),
+        );
+    let inner = block.inner(area);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+
+    f.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::raw("status: "),
+            Span::styled(
+                app.status.state.to_string(),
+                Style::default().fg(connection_color(&app.status.state)),
+            ),
+        ])),
+        chunks[0],
+    );
+    f.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("address: ", Style::default().fg(Color::Cyan)),
+            Span::raw(connect_dialog.input.as_str()),
+            Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+        ])),
+        chunks[1],
+    );
+    if let Some(error) = &connect_dialog.error {
+        f.render_widget(
+            Paragraph::new(Span::styled(
+                error.as_str(),
+                Style::default().fg(Color::Red),
+            )),
+            chunks[2],
+        );
+    }
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            "recent servers:",
+            Style::default().fg(theme.muted),
+        )),
+        chunks[3],
+    );
+
+    let servers: Vec<ListItem> = app
+        .config
+        .servers
+        .iter()
+        .map(|server| ListItem::new(server.as_str()))
+        .collect();
+    let list = List::new(servers).highlight_style(Style::default().fg(theme.highlight));
+    let mut state = ListState::default().with_selected(connect_dialog.selected);
+    f.render_stateful_widget(list, chunks[4], &mut state);
+}
+
+fn draw_search_bar(f: &mut Frame, app: &App, area: Rect) {
+    let Some(search) = &app.search else {
+        return;
+    };
+    let theme = app.config.theme.theme();
+    let muted = Style::default().fg(theme.muted);
+
+    let mut spans = vec![
+        Span::styled("/", Style::default().fg(Color::Cyan)),
+        Span::raw(search.query.as_str()),
+    ];
+    if search.editing {
+        spans.push(Span::styled(
+            "_",
+            Style::default().add_modifier(Modifier::SLOW_BLINK),
+        ));
+    }
+    spans.push(Span::styled(format!("  [{}]  ", search.mode), muted));
+    match &search.error {
+        Some(error) => spans.push(Span::styled(
+            error.lines().last().unwrap_or_default().to_string(),
+            Style::default().fg(Color::Red),
+        )),
+        None if search.query.is_empty() => {}
+        None => spans.push(Span::styled(
+            format!("{} matching lines", app.wrapped.match_count()),
+            Style::default().fg(theme.highlight),
+        )),
+    }
+    let keys = &app.config.keybindings;
+    spans.push(Span::styled(
+        if search.editing {
+            String::from("  Enter done | Tab mode | Esc cancel")
+        } else {
+            format!(
+                "  {}/{} next/previous | Esc clear",
+                keys.search_next, keys.search_previous
+            )
+        },
+        muted,
+    ));
+
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+fn draw_filter_editor(f: &mut Frame, app: &App) {
+    let Some(editor) = &app.filter_editor else {
+        return;
+    };
+    let theme = app.config.theme.theme();
+    let muted = Style::default().fg(theme.muted);
+
+    let area = centered_rect(70, 60, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled(
+            "Filter",
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .title(
+            block::Title::from(Span::styled(
+                " Enter apply | Tab switch field | Up/Down saved | Del delete saved | Esc close ",
+                muted,
+            ))
+            .position(block::Position::Bottom),
+        );
+    let inner = block.inner(area);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(2),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+
+    for (index, (label, value, field)) in [
+        ("name: ", &editor.name, FilterField::Name),
+        ("rules: ", &editor.rules, FilterField::Rules),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let mut spans = vec![
+            Span::styled(label, Style::default().fg(Color::Cyan)),
+            Span::raw(value.as_str()),
+        ];
+        if editor.focus == field {
+            spans.push(Span::styled(
+                "_",
+                Style::default().add_modifier(Modifier::SLOW_BLINK),
+            ));
+        }
+        f.render_widget(Paragraph::new(Line::from(spans)), chunks[index]);
+    }
+    if let Some(error) = &editor.error {
+        f.render_widget(
+            Paragraph::new(Span::styled(
+                error.as_str(),
+                Style::default().fg(Color::Red),
+            )),
+            chunks[2],
+        );
+    }
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            "rules: [+|-]guild:ID channel:ID user:ID kind:NewMessage bot:yes|no \
+             content:\"regex\", + includes and - excludes, an empty name isn't saved",
+            muted,
+        ))
+        .wrap(Wrap { trim: true }),
+        chunks[3],
+    );
+    f.render_widget(
+        Paragraph::new(Span::styled("saved filters:", muted)),
+        chunks[4],
+    );
+
+    let filters: Vec<ListItem> = app
+        .config
+        .filters
+        .iter()
+        .map(|saved| {
+            ListItem::new(Line::from(vec![
+                Span::raw(saved.name.as_str()),
+                Span::styled(format!("  {}", filter::format_rules(&saved.rules)), muted),
+            ]))
+        })
+        .collect();
+    let list = List::new(filters).highlight_style(Style::default().fg(theme.highlight));
+    let mut state = ListState::default().with_selected(editor.selected);
+    f.render_stateful_widget(list, chunks[5], &mut state);
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
 }
 
 fn draw_first_tab(f: &mut Frame, app: &mut App, area: Rect) {
     draw_events(f, app, area);
 }
 
-// the events on the first page.
-// I wonder if I can make it so it only redraws messages
-// if they have actually changed or the window has updated?
-fn draw_events(f: &mut Frame, app: &mut App, area: Rect, ) {
-    let messages = MESSAGES.lock().unwrap();
-    let text: Vec<Line<'_>> = messages
+fn draw_voice_tab(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.config.theme.theme();
+    let voice = &app.voice;
+
+    let mut guilds: Vec<_> = voice
+        .guilds
         .iter()
-        .flat_map(|lines| lines.iter())
-        .cloned()
+        .filter(|(_, guild)| !guild.channels.is_empty())
         .collect();
+    guilds.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
 
-    let border_width: u16 = if app.logs_border { 2 } else { 0 };
-    let mut new_text: Vec<Line<'_>> = Vec::new();
-    let max_width = area.width - border_width;
-
-    for line in text.clone() {
-        if line.width() <= max_width.into() {
-            new_text.push(line)
+    let mut text: Vec<Line> = Vec::new();
+    for (guild_id, guild) in guilds {
+        let name = if guild.name.is_empty() {
+            guild_id.to_string()
         } else {
-            let funky = split_line(line.spans, max_width.into());
-            for fun in funky {
-                new_text.push(fun)
+            guild.name.clone()
+        };
+        text.push(Line::from(Span::styled(
+            name,
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        )));
+
+        let mut channels: Vec<_> = guild.channels.values().collect();
+        channels.sort_by(|a, b| a.name.cmp(&b.name));
+        for channel in channels {
+            text.push(Line::from(vec![
+                Span::raw(format!("  #{}", channel.name)),
+                Span::styled(
+                    format!(" ({})", channel.members.len()),
+                    Style::default().fg(theme.muted),
+                ),
+            ]));
+            for member in channel.members.values() {
+                let mut line = vec![Span::raw(format!("    {}", member.name))];
+                for (enabled, flag, color) in [
+                    (member.muted, " [muted]", Color::Red),
+                    (member.deafened, " [deafened]", Color::Red),
+                    (member.streaming, " [live]", Color::LightMagenta),
+                    (member.video, " [video]", Color::LightMagenta),
+                ] {
+                    if enabled {
+                        line.push(Span::styled(flag, Style::default().fg(color)));
+                    }
+                }
+                text.push(Line::from(line));
             }
         }
     }
 
-    app.vertical_scroll_state = app.vertical_scroll_state.content_length(new_text.len());
+    if text.is_empty() {
+        text.push(Line::from(Span::styled(
+            "Nobody has been seen in a voice channel yet.",
+            Style::default().fg(theme.muted),
+        )));
+    }
+
+    let mut paragraph = Paragraph::new(text);
+    if app.logs_border {
+        paragraph = paragraph.block(
+            Block::default().borders(Borders::ALL).title(Span::styled(
+                "Voice",
+                Style::default()
+                    .fg(theme.title)
+                    .add_modifier(Modifier::BOLD),
+            )),
+        );
+    }
+    f.render_widget(paragraph, area);
+}
+
+fn draw_diagnostics_tab(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.config.theme.theme();
+    let diagnostics = &app.diagnostics;
 
-    let logs_height = area.height - border_width;
+    let mut counts = vec![Span::styled(
+        "failures by kind: ",
+        Style::default().fg(theme.muted),
+    )];
+    if diagnostics.counts.is_empty() {
+        counts.push(Span::raw("none"));
+    }
+    for (kind, count) in &diagnostics.counts {
+        counts.push(Span::styled(
+            format!("{kind}: {count}  "),
+            Style::default().fg(Color::Red),
+        ));
+    }
 
-    app.vertical_scroll = if new_text.len() > logs_height as usize {
-        new_text.len() - logs_height as usize
+    let mut text = vec![Line::from(counts), Line::default()];
+    for failure in &diagnostics.failures {
+        text.push(Line::from(vec![
+            Span::styled(
+                format!("[{} ago] ", format_duration(failure.received.elapsed())),
+                Style::default().fg(theme.muted),
+            ),
+            Span::styled(
+                format!("{} ", failure.kind),
+                Style::default().fg(Color::Red),
+            ),
+            Span::styled(
+                failure.variant.as_deref().unwrap_or("<no variant>"),
+                Style::default().fg(theme.highlight),
+            ),
+            Span::raw(format!(" at {}: {}", failure.path, failure.message)),
+        ]));
+        text.push(Line::from(Span::styled(
+            format!("  {}", failure.payload),
+            Style::default().fg(theme.muted),
+        )));
+    }
+
+    let mut paragraph = Paragraph::new(text).wrap(Wrap { trim: false });
+    if app.logs_border {
+        paragraph = paragraph.block(
+            Block::default().borders(Borders::ALL).title(Span::styled(
+                "Diagnostics",
+                Style::default()
+                    .fg(theme.title)
+                    .add_modifier(Modifier::BOLD),
+            )),
+        );
+    }
+    f.render_widget(paragraph, area);
+}
+
+fn draw_alerts_tab(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.config.theme.theme();
+    let muted = Style::default().fg(theme.muted);
+    let watchlist = &app.watchlist;
+
+    let mut text: Vec<Line> = Vec::new();
+    if watchlist.rules.is_empty() {
+        text.push(Line::from(Span::styled(
+            "The watchlist is empty, add words, regexes, users or channels to `watchlist` in the config.",
+            muted,
+        )));
     } else {
-        0
-    };
+        let mut hits = vec![Span::styled("hits: ", muted)];
+        for (rule, count) in watchlist.rules.iter().zip(&watchlist.hits) {
+            hits.push(Span::styled(
+                format!("{rule}: {count}  "),
+                Style::default().fg(theme.highlight),
+            ));
+        }
+        text.push(Line::from(hits));
+    }
+    text.push(Line::from(Span::styled(
+        format!(
+            "bell {} ({})",
+            if app.config.watch_bell { "on" } else { "off" },
+            app.config.keybindings.toggle_bell
+        ),
+        muted,
+    )));
+
+    for alert in &watchlist.alerts {
+        text.push(Line::default());
+        let rules: Vec<String> = alert
+            .rules
+            .iter()
+            .map(|&index| watchlist.rules[index].to_string())
+            .collect();
+        text.push(Line::from(Span::styled(
+            format!(
+                "[{} ago] matched {}",
+                format_duration(alert.received.elapsed()),
+                rules.join(", ")
+            ),
+            muted,
+        )));
+        text.extend(alert.lines.iter().cloned());
+    }
 
-    app.vertical_scroll_state.position(new_text.len());
+    let mut paragraph = Paragraph::new(text).wrap(Wrap { trim: false });
+    if app.logs_border {
+        paragraph = paragraph.block(
+            Block::default().borders(Borders::ALL).title(Span::styled(
+                "Alerts",
+                Style::default()
+                    .fg(theme.title)
+                    .add_modifier(Modifier::BOLD),
+            )),
+        );
+    }
+    f.render_widget(paragraph, area);
+}
 
+// the events on the first page.
+// only new events are wrapped, and only the lines on screen are handed to the paragraph.
+fn draw_events(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.config.theme.theme();
     let block = Block::default().borders(Borders::ALL).title(Span::styled(
         "Events",
         Style::default()
-            .fg(Color::Magenta)
+            .fg(theme.title)
             .add_modifier(Modifier::BOLD),
     ));
+    let inner = if app.logs_border {
+        block.inner(area)
+    } else {
+        area
+    };
 
-    let mut paragraph = Paragraph::new(new_text).scroll((app.vertical_scroll as u16, 0));
+    // without a border the scrollbar takes the last column of the text.
+    let max_width = if app.logs_border {
+        inner.width
+    } else {
+        inner.width.saturating_sub(1)
+    };
+    let changes = app.wrapped.update(
+        &app.events,
+        &app.config,
+        app.filter.as_ref(),
+        max_width.into(),
+    );
+    // keep the same text on screen while scrolled back, as the oldest events go, and
+    // only count events that show up as new below it.
+    if !app.follow {
+        app.vertical_scroll = app.vertical_scroll.saturating_sub(changes.dropped);
+        app.new_below += changes.added;
+    }
+    if let Some(search) = &mut app.search {
+        search.anchor = search.anchor.saturating_sub(changes.dropped);
+    }
+
+    let evicted = (app.events.evicted > 0).then(|| {
+        Line::from(Span::styled(
+            format!(
+                "… {} older events were dropped (keeping {} events{})",
+                app.events.evicted,
+                app.events.capacity,
+                app.events
+                    .memory_budget
+                    .map_or(String::new(), |budget| format!(
+                        ", {} MiB",
+                        budget / 1024 / 1024
+                    ))
+            ),
+            Style::default().fg(theme.muted),
+        ))
+    });
+    let total_lines = usize::from(evicted.is_some()) + app.wrapped.total_lines();
+
+    let logs_height = inner.height as usize;
+    app.events_height = logs_height;
+    let max_scroll = total_lines.saturating_sub(logs_height);
+    if app.follow || app.vertical_scroll >= max_scroll {
+        app.follow = true;
+        app.new_below = 0;
+        app.vertical_scroll = max_scroll;
+    }
+
+    // each visible line with the event it's for.
+    let mut visible: Vec<(Option<u64>, Line<'_>)> = Vec::new();
+    let mut start = app.vertical_scroll;
+    if let Some(evicted) = evicted {
+        if start == 0 {
+            visible.push((None, evicted));
+        } else {
+            start -= 1;
+        }
+    }
+    let remaining = logs_height - visible.len().min(logs_height);
+    visible.extend(
+        app.wrapped
+            .lines_from(start)
+            .take(remaining)
+            .map(|(seq, line)| (Some(seq), line.clone())),
+    );
+    app.events_area = inner;
+    app.event_rows = visible.iter().map(|(seq, _)| *seq).collect();
+
+    if let Some(regex) = app.search.as_ref().and_then(|search| search.regex()) {
+        let current = app
+            .search
+            .as_ref()
+            .and_then(|search| search.current)
+            .and_then(|(seq, nth)| app.wrapped.line_index(seq, nth));
+        let matched = Style::default().add_modifier(Modifier::REVERSED);
+        let current_match = Style::default().bg(theme.highlight).fg(Color::Black);
+        // the rows before the first event line are the dropped events notice.
+        let first_row = visible.iter().take_while(|(seq, _)| seq.is_none()).count();
+        for (row, (_, line)) in visible.iter_mut().enumerate().skip(first_row) {
+            let index = start + row - first_row;
+            let style = if current == Some(index) {
+                current_match
+            } else {
+                matched
+            };
+            *line = search::highlight(std::mem::take(line), regex, style);
+        }
+    }
+
+    // scaled so the thumb reaches the end of the track at the bottom.
+    app.vertical_scroll_state = app
+        .vertical_scroll_state
+        .content_length(total_lines)
+        .viewport_content_length(logs_height)
+        .position(
+            (app.vertical_scroll * total_lines)
+                .checked_div(max_scroll)
+                .unwrap_or(0),
+        );
+
+    let lines: Vec<Line<'_>> = visible.into_iter().map(|(_, line)| line).collect();
+    let mut paragraph = Paragraph::new(lines);
 
     if app.logs_border {
         paragraph = paragraph.block(block);
     }
 
     f.render_widget(paragraph, area);
+
+    if max_scroll > 0 {
+        let scrollbar_area = if app.logs_border {
+            area.inner(&Margin {
+                vertical: 1,
+                horizontal: 0,
+            })
+        } else {
+            area
+        };
+        f.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .thumb_style(Style::default().fg(theme.highlight)),
+            scrollbar_area,
+            &mut app.vertical_scroll_state,
+        );
+    }
+
+    if app.new_below > 0 {
+        let indicator = Line::from(Span::styled(
+            format!(" {} new events below, End to follow ", app.new_below),
+            Style::default().bg(theme.status_bg).fg(theme.status_fg),
+        ));
+        let width = (indicator.width() as u16).min(max_width);
+        let indicator_area = Rect {
+            x: inner.x + max_width.saturating_sub(width),
+            y: inner.bottom().saturating_sub(1),
+            width,
+            height: 1.min(inner.height),
+        };
+        f.render_widget(Paragraph::new(indicator), indicator_area);
+    }
 }
 
-// I should probably try and optimise this.
-fn split_line(spans: Vec<Span<'_>>, max_width: usize) -> Vec<Line<'_>> {
-    let mut lines: Vec<Line<'_>> = Vec::new();
-    let mut current_line = String::new();
-    let mut current_width = 0;
+fn draw_status_bar(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.config.theme.theme();
+    let status = &mut app.status;
+    let separator = Span::styled(" | ", Style::default().fg(theme.muted));
 
-    let mut spans_to_push: Vec<Span> = Vec::new();
+    let mut spans = vec![
+        Span::raw(format!(" {} ", status.server)),
+        Span::styled(
+            status.state.to_string(),
+            Style::default().fg(connection_color(&status.state)),
+        ),
+    ];
 
-    for span in spans.clone() {
-        let span_chars: Vec<char> = span.content.chars().collect();
+    if let Some(uptime) = status.uptime() {
+        spans.push(separator.clone());
+        spans.push(Span::raw(format!("up {}", format_duration(uptime))));
+    }
 
-        for &c in span_chars.iter() {
-            if current_width < max_width {
-                current_line.push(c);
-                current_width += 1;
-            } else {
-                // line is full so clear it.
-                let span = Span::styled(current_line.clone(), span.style);
-                spans_to_push.push(span);
-                lines.push(Line::from(spans_to_push.clone()));
-                current_line.clear();
-                spans_to_push.clear();
-                // add the character to the line.
-                current_line.push(c);
-                current_width = 1;
-            }
-        }
-        if !current_line.is_empty() {
-            // is not empty, but isn't full either.
-            // We have reached the end of the span.
-            let span = Span::styled(current_line.clone(), span.style);
-            current_line.clear();
-            spans_to_push.push(span);
-        }
+    spans.push(separator.clone());
+    spans.push(Span::raw(match status.last_event {
+        Some(at) => format!("last event {} ago", format_duration(at.elapsed())),
+        None => String::from("no events yet"),
+    }));
+
+    spans.push(separator.clone());
+    spans.push(Span::raw(format!(
+        "{:.1} events/s",
+        status.events_per_sec()
+    )));
+
+    if status.dropped > 0 || status.unparsed > 0 {
+        spans.push(separator.clone());
+        spans.push(Span::styled(
+            format!("dropped {} / unparsed {}", status.dropped, status.unparsed),
+            Style::default().fg(Color::Red),
+        ));
+    }
+
+    if let Some(filter) = &app.filter {
+        spans.push(separator.clone());
+        spans.push(Span::styled(
+            match filter.name.as_str() {
+                "" => String::from("filtered"),
+                name => format!("filter: {name}"),
+            },
+            Style::default().fg(theme.highlight),
+        ));
+    }
+
+    if !app.config.mouse_capture {
+        spans.push(separator.clone());
+        spans.push(Span::styled(
+            format!("mouse released ({})", app.config.keybindings.toggle_mouse),
+            Style::default().fg(theme.highlight),
+        ));
+    }
+
+    if let Some(error) = &app.config_error {
+        spans.push(separator);
+        spans.push(Span::styled(
+            format!("config: {error}"),
+            Style::default().fg(Color::Red),
+        ));
     }
-    let line = Line::from(spans_to_push);
-    lines.push(line);
 
-    lines
+    let paragraph = Paragraph::new(Line::from(spans))
+        .style(Style::default().bg(theme.status_bg).fg(theme.status_fg));
+    f.render_widget(paragraph, area);
+}
+
+fn format_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, (secs / 60) % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}h{minutes:02}m{seconds:02}s")
+    } else if minutes > 0 {
+        format!("{minutes}m{seconds:02}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+fn connection_color(state: &ConnectionState) -> Color {
+    match state {
+        ConnectionState::Connected => Color::Green,
+        ConnectionState::Connecting { .. } | ConnectionState::Retrying { .. } => Color::Yellow,
+        ConnectionState::GaveUp { .. } => Color::Red,
+    }
 }
@@ -0,0 +1,140 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serenity::all::{ChannelId, GuildId, UserId, VoiceState};
+
+/// A single change between two voice states of a user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceTransition {
+    Join(ChannelId),
+    Leave(ChannelId),
+    Move { from: ChannelId, to: ChannelId },
+    ServerMute(bool),
+    ServerDeafen(bool),
+    SelfMute(bool),
+    SelfDeafen(bool),
+    Stream(bool),
+    Video(bool),
+}
+
+/// Works out everything that changed between `old` and `new`.
+///
+/// Without an old state we can only tell that the user is now in a channel.
+pub fn classify(old: Option<&VoiceState>, new: &VoiceState) -> Vec<VoiceTransition> {
+    let mut transitions = Vec::new();
+
+    let Some(old) = old else {
+        if let Some(channel_id) = new.channel_id {
+            transitions.push(VoiceTransition::Join(channel_id));
+        }
+        return transitions;
+    };
+
+    match (old.channel_id, new.channel_id) {
+        (None, Some(to)) => transitions.push(VoiceTransition::Join(to)),
+        (Some(from), None) => {
+            transitions.push(VoiceTransition::Leave(from));
+            // everything else resets when leaving, so it isn't interesting.
+            return transitions;
+        }
+        (Some(from), Some(to)) if from != to => {
+            transitions.push(VoiceTransition::Move { from, to });
+        }
+        _ => {}
+    }
+
+    if old.mute != new.mute {
+        transitions.push(VoiceTransition::ServerMute(new.mute));
+    }
+    if old.deaf != new.deaf {
+        transitions.push(VoiceTransition::ServerDeafen(new.deaf));
+    }
+    if old.self_mute != new.self_mute {
+        transitions.push(VoiceTransition::SelfMute(new.self_mute));
+    }
+    if old.self_deaf != new.self_deaf {
+        transitions.push(VoiceTransition::SelfDeafen(new.self_deaf));
+    }
+    if old.self_stream.unwrap_or(false) != new.self_stream.unwrap_or(false) {
+        transitions.push(VoiceTransition::Stream(new.self_stream.unwrap_or(false)));
+    }
+    if old.self_video != new.self_video {
+        transitions.push(VoiceTransition::Video(new.self_video));
+    }
+
+    transitions
+}
+
+/// Who is in which voice channel, built up from voice state updates.
+///
+/// This only knows about users who changed state since the client started.
+#[derive(Debug, Default)]
+pub struct VoiceOccupancy {
+    pub guilds: HashMap<GuildId, GuildVoice>,
+}
+
+#[derive(Debug, Default)]
+pub struct GuildVoice {
+    pub name: String,
+    pub channels: HashMap<ChannelId, VoiceChannel>,
+}
+
+#[derive(Debug, Default)]
+pub struct VoiceChannel {
+    pub name: String,
+    pub members: BTreeMap<UserId, VoiceMember>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VoiceMember {
+    pub name: String,
+    pub muted: bool,
+    pub deafened: bool,
+    pub streaming: bool,
+    pub video: bool,
+}
+
+impl VoiceOccupancy {
+    /// Moves the user in `new` to its channel, removing them from wherever they were before.
+    pub fn apply(
+        &mut self,
+        new: &VoiceState,
+        guild_name: Option<&str>,
+        channel_name: Option<&str>,
+        user_name: &str,
+    ) {
+        let Some(guild_id) = new.guild_id else {
+            return;
+        };
+        let guild = self.guilds.entry(guild_id).or_default();
+        if let Some(guild_name) = guild_name {
+            guild.name = guild_name.to_string();
+        }
+
+        for channel in guild.channels.values_mut() {
+            channel.members.remove(&new.user_id);
+        }
+        guild
+            .channels
+            .retain(|_, channel| !channel.members.is_empty());
+
+        let Some(channel_id) = new.channel_id else {
+            return;
+        };
+        let channel = guild.channels.entry(channel_id).or_default();
+        if let Some(channel_name) = channel_name {
+            channel.name = channel_name.to_string();
+        } else if channel.name.is_empty() {
+            channel.name = channel_id.to_string();
+        }
+        channel.members.insert(
+            new.user_id,
+            VoiceMember {
+                name: user_name.to_string(),
+                muted: new.mute || new.self_mute,
+                deafened: new.deaf || new.self_deaf,
+                streaming: new.self_stream.unwrap_or(false),
+                video: new.self_video,
+            },
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use std::{collections::VecDeque, fmt, time::Instant};
+
+use ratatui::text::Line;
+use regex::{Regex, RegexBuilder};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    event::WebSocketEvent,
+    store::{Payload, StoredEvent},
+};
+
+/// How many alerts are kept for the alerts tab.
+const MAX_ALERTS: usize = 200;
+
+/// Something to look out for in new and edited messages, written in the config as
+/// e.g. `{ word = "jamespy" }` or `{ user = 1234 }`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Watch {
+    /// A whole word, ignoring case.
+    Word(String),
+    Regex(String),
+    User(u64),
+    Channel(u64),
+}
+
+impl fmt::Display for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Watch::Word(word) => write!(f, "word \"{word}\""),
+            Watch::Regex(pattern) => write!(f, "regex /{pattern}/"),
+            Watch::User(id) => write!(f, "user {id}"),
+            Watch::Channel(id) => write!(f, "channel {id}"),
+        }
+    }
+}
+
+/// A message that matched the watchlist, rendered when it arrived so it outlives the history.
+pub struct Alert {
+    pub received: Instant,
+    /// Indexes into [`Watchlist::rules`] of every rule it matched.
+    pub rules: Vec<usize>,
+    pub lines: Vec<Line<'static>>,
+}
+
+/// The watchlist from the config, with how often each rule matched and the recent alerts.
+#[derive(Default)]
+pub struct Watchlist {
+    pub rules: Vec<Watch>,
+    pub hits: Vec<u64>,
+    pub alerts: VecDeque<Alert>,
+    matchers: Vec<Matcher>,
+}
+
+enum Matcher {
+    Text(Regex),
+    User(u64),
+    Channel(u64),
+}
+
+impl Watchlist {
+    pub fn new(rules: &[Watch]) -> Result<Watchlist, regex::Error> {
+        let matchers = rules
+            .iter()
+            .map(|rule| {
+                Ok(match rule {
+                    Watch::Word(word) => Matcher::Text(word_regex(word)?),
+                    Watch::Regex(pattern) => Matcher::Text(Regex::new(pattern)?),
+                    Watch::User(id) => Matcher::User(*id),
+                    Watch::Channel(id) => Matcher::Channel(*id),
+                })
+            })
+            .collect::<Result<_, regex::Error>>()?;
+
+        Ok(Watchlist {
+            rules: rules.to_vec(),
+            hits: vec![0; rules.len()],
+            alerts: VecDeque::new(),
+            matchers,
+        })
+    }
+
+    /// The rules a new or edited message matches, counting a hit for each.
+    ///
+    /// Only edits that change what a person wrote are checked, the same ones the events
+    /// view shows, so embeds loading in don't alert again for the same message.
+    pub fn check(&mut self, event: &StoredEvent) -> Vec<usize> {
+        let Payload::Known(known) = &event.payload else {
+            return Vec::new();
+        };
+        let checked = match &**known {
+            WebSocketEvent::NewMessage { .. } => true,
+            WebSocketEvent::MessageEdit {
+                old_if_available: Some(old),
+                new: Some(new),
+                ..
+            } => !new.author.bot && old.content != new.content,
+            _ => false,
+        };
+        if !checked {
+            return Vec::new();
+        }
+
+        let matched: Vec<usize> = self
+            .matchers
+            .iter()
+            .enumerate()
+            .filter(|(_, matcher)| matcher.matches(event))
+            .map(|(index, _)| index)
+            .collect();
+        for &index in &matched {
+            self.hits[index] += 1;
+        }
+        matched
+    }
+
+    pub fn record(&mut self, alert: Alert) {
+        self.alerts.push_front(alert);
+        self.alerts.truncate(MAX_ALERTS);
+    }
+}
+
+/// Matches `word` on its own, ignoring case. `\b` only works next to a word character, so
+/// an edge like the `@` of `@everyone` or the end of `c++` is left open.
+fn word_regex(word: &str) -> Result<Regex, regex::Error> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let start = if word.starts_with(is_word) { r"\b" } else { "" };
+    let end = if word.ends_with(is_word) { r"\b" } else { "" };
+    RegexBuilder::new(&format!("{start}{}{end}", regex::escape(word)))
+        .case_insensitive(true)
+        .build()
+}
+
+impl Matcher {
+    fn matches(&self, event: &StoredEvent) -> bool {
+        match self {
+            Matcher::Text(regex) => event
+                .content()
+                .is_some_and(|content| regex.is_match(content)),
+            Matcher::User(id) => event.user_id.is_some_and(|user_id| user_id.get() == *id),
+            Matcher::Channel(id) => event
+                .channel_id
+                .is_some_and(|channel_id| channel_id.get() == *id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_matches_whole_words() {
+        let regex = word_regex("jamespy").unwrap();
+        assert!(regex.is_match("hey JamesPy, you there?"));
+        assert!(!regex.is_match("jamespy_bot"));
+        assert!(!regex.is_match("notjamespy"));
+    }
+
+    #[test]
+    fn word_with_punctuation_at_the_edges_matches() {
+        let everyone = word_regex("@everyone").unwrap();
+        assert!(everyone.is_match("@everyone look"));
+        assert!(everyone.is_match("hi @everyone!"));
+        assert!(!everyone.is_match("@everyones"));
+
+        let cpp = word_regex("c++").unwrap();
+        assert!(cpp.is_match("who knows c++?"));
+        assert!(cpp.is_match("C++"));
+        assert!(!cpp.is_match("abc++"));
+    }
+}
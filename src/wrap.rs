@@ -0,0 +1,546 @@
+use std::{collections::VecDeque, ops::Range};
+
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+};
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    config::Config, event_handlers, filter::ActiveFilter, search::line_text, store::EventStore,
+};
+
+/// The wrapped lines of every rendered event, so a draw only wraps what changed.
+///
+/// Only events newer than the last update are looked at, everything is redone when the
+/// width or anything that changes how events are rendered does. Changing the filter needs
+/// an [`invalidate`](WrapCache::invalidate).
+///
+/// The search is run over each event as it's wrapped too, so finding matches doesn't
+/// need to go through every line again.
+#[derive(Default)]
+pub struct WrapCache {
+    key: Option<CacheKey>,
+    entries: VecDeque<CachedEvent>,
+    total_lines: usize,
+    /// The newest event looked at, whether or not it passed the filter or had any lines.
+    last_seen: Option<u64>,
+    search: Option<Regex>,
+    match_count: usize,
+}
+
+/// Everything the cached lines depend on besides the events themselves.
+#[derive(PartialEq, Eq, Clone, Copy)]
+struct CacheKey {
+    width: usize,
+    collapse_reactions: bool,
+    expand_unknown_events: bool,
+}
+
+struct CachedEvent {
+    seq: u64,
+    lines: Vec<Line<'static>>,
+    /// The text of each rendered line, searched before wrapping so a match can span a break.
+    texts: Vec<String>,
+    /// For each wrapped line, the rendered line it's from and the bytes of its text it shows.
+    sources: Vec<(usize, Range<usize>)>,
+    /// Which of the wrapped lines match the search.
+    matches: Vec<usize>,
+}
+
+impl CachedEvent {
+    fn new(seq: u64, rendered: &[Line<'_>], width: usize) -> CachedEvent {
+        let mut entry = CachedEvent {
+            seq,
+            lines: Vec::new(),
+            texts: Vec::with_capacity(rendered.len()),
+            sources: Vec::new(),
+            matches: Vec::new(),
+        };
+        for (index, line) in rendered.iter().enumerate() {
+            let text = line_text(line);
+            let wrapped = wrap(line, width);
+            // a line goes on to where the next starts, including whitespace dropped at the break.
+            let ends: Vec<usize> = wrapped
+                .iter()
+                .skip(1)
+                .map(|(_, start)| *start)
+                .chain([text.len()])
+                .collect();
+            for ((line, start), end) in wrapped.into_iter().zip(ends) {
+                entry.lines.push(line);
+                entry.sources.push((index, start..end));
+            }
+            entry.texts.push(text);
+        }
+        entry
+    }
+
+    fn find_matches(&mut self, regex: Option<&Regex>) {
+        self.matches.clear();
+        let Some(regex) = regex else {
+            return;
+        };
+        for (index, text) in self.texts.iter().enumerate() {
+            for m in regex.find_iter(text).filter(|m| !m.is_empty()) {
+                let lines = self
+                    .sources
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (source, range))| {
+                        *source == index && range.start < m.end() && m.start() < range.end
+                    });
+                self.matches.extend(lines.map(|(nth, _)| nth));
+            }
+        }
+        self.matches.sort_unstable();
+        self.matches.dedup();
+    }
+}
+
+/// What an [`update`](WrapCache::update) changed.
+pub struct Changes {
+    /// How many lines of evicted events were dropped from the front, so a position in the
+    /// lines can be kept on the same text.
+    pub dropped: usize,
+    /// How many events were shown for the first time, not counting a reaction burst
+    /// growing or everything being wrapped again.
+    pub added: usize,
+}
+
+impl WrapCache {
+    /// Brings the cache up to date with the `events` passing `filter`, wrapped to `width`.
+    pub fn update(
+        &mut self,
+        events: &EventStore,
+        config: &Config,
+        filter: Option<&ActiveFilter>,
+        width: usize,
+    ) -> Changes {
+        let key = CacheKey {
+            width,
+            collapse_reactions: config.collapse_reactions,
+            expand_unknown_events: config.expand_unknown_events,
+        };
+        let rebuilt = self.key != Some(key);
+        if rebuilt {
+            self.key = Some(key);
+            self.entries.clear();
+            self.total_lines = 0;
+            self.last_seen = None;
+            self.match_count = 0;
+        }
+
+        let first_seq = events.first_seq().unwrap_or(u64::MAX);
+        let mut dropped = 0;
+        while self
+            .entries
+            .front()
+            .is_some_and(|entry| entry.seq < first_seq)
+        {
+            if let Some(entry) = self.entries.pop_front() {
+                self.total_lines -= entry.lines.len();
+                self.match_count -= entry.matches.len();
+                dropped += entry.lines.len();
+            }
+        }
+
+        let unseen = self.last_seen.map_or(0, |seq| seq + 1);
+        let Some(first_unseen) = events.since(unseen).next() else {
+            return Changes { dropped, added: 0 };
+        };
+
+        // the newest entry is rendered again when it's a reaction burst the new events
+        // might grow, which only goes back as far as the burst window.
+        let burst = self.entries.back().is_some_and(|last| {
+            events
+                .since(last.seq)
+                .next()
+                .is_some_and(|first| event_handlers::may_join_burst(first, first_unseen, config))
+        });
+        let last = if burst {
+            self.entries.pop_back().map(|last| {
+                self.total_lines -= last.lines.len();
+                self.match_count -= last.matches.len();
+                last.seq
+            })
+        } else {
+            None
+        };
+        let since = last.unwrap_or(unseen);
+        let last_seen = &mut self.last_seen;
+        let shown = events
+            .since(since)
+            .inspect(|event| *last_seen = Some(event.seq))
+            .filter(|event| filter.is_none_or(|filter| filter.matches(event)));
+        let mut added = 0;
+        for rendered in event_handlers::render_events(shown, config) {
+            if !rebuilt && last != Some(rendered.seq) {
+                added += 1;
+            }
+            let mut entry = CachedEvent::new(rendered.seq, &rendered.lines, width);
+            entry.find_matches(self.search.as_ref());
+            self.total_lines += entry.lines.len();
+            self.match_count += entry.matches.len();
+            self.entries.push_back(entry);
+        }
+        Changes { dropped, added }
+    }
+
+    /// Searches every cached event for `regex`, or stops searching, after the query changed.
+    pub fn set_search(&mut self, regex: Option<&Regex>) {
+        self.search = regex.cloned();
+        self.match_count = 0;
+        for entry in &mut self.entries {
+            entry.find_matches(self.search.as_ref());
+            self.match_count += entry.matches.len();
+        }
+    }
+
+    /// How many wrapped lines match the search.
+    pub fn match_count(&self) -> usize {
+        self.match_count
+    }
+
+    /// The indexes of the wrapped lines matching the search, in order.
+    pub fn matching_lines(&self) -> impl Iterator<Item = usize> + '_ {
+        let mut start = 0;
+        self.entries.iter().flat_map(move |entry| {
+            let first = start;
+            start += entry.lines.len();
+            entry.matches.iter().map(move |nth| first + nth)
+        })
+    }
+
+    /// Drops every cached line, they're all rendered again on the next update.
+    pub fn invalidate(&mut self) {
+        self.key = None;
+    }
+
+    pub fn total_lines(&self) -> usize {
+        self.total_lines
+    }
+
+    /// The index of the `nth` wrapped line of event `seq`, if it's still shown.
+    pub fn line_index(&self, seq: u64, nth: usize) -> Option<usize> {
+        let mut index = 0;
+        for entry in &self.entries {
+            if entry.seq == seq {
+                return (nth < entry.lines.len()).then_some(index + nth);
+            }
+            index += entry.lines.len();
+        }
+        None
+    }
+
+    /// The event the wrapped line at `index` is for, and which of its lines it is.
+    pub fn line_at(&self, index: usize) -> Option<(u64, usize)> {
+        let mut start = 0;
+        for entry in &self.entries {
+            if index < start + entry.lines.len() {
+                return Some((entry.seq, index - start));
+            }
+            start += entry.lines.len();
+        }
+        None
+    }
+
+    /// The wrapped lines from line `start` on, with the sequence number of their event.
+    pub fn lines_from(&self, start: usize) -> impl Iterator<Item = (u64, &Line<'static>)> {
+        let mut skipped = 0;
+        let mut first_entry = self.entries.len();
+        for (index, entry) in self.entries.iter().enumerate() {
+            if skipped + entry.lines.len() > start {
+                first_entry = index;
+                break;
+            }
+            skipped += entry.lines.len();
+        }
+
+        self.entries
+            .range(first_entry..)
+            .flat_map(|entry| entry.lines.iter().map(|line| (entry.seq, line)))
+            .skip(start - skipped)
+    }
+}
+
+/// A grapheme cluster with the style of the span it came from.
+struct Grapheme<'a> {
+    symbol: &'a str,
+    style: Style,
+    width: usize,
+    /// Where it starts in the text of the whole line.
+    offset: usize,
+}
+
+impl Grapheme<'_> {
+    fn is_whitespace(&self) -> bool {
+        self.symbol.chars().all(char::is_whitespace)
+    }
+}
+
+/// Wraps `line` to `max_width` columns, breaking between words where it can.
+///
+/// Continuation lines are indented to line up after the line's prefix, the leading spans
+/// up to and including the first one ending in `": "`, like `[guild] [#channel] user: `.
+/// Graphemes are never split, and a wide character goes to the next line rather than
+/// overflowing.
+pub fn wrap_line(line: &Line<'_>, max_width: usize) -> Vec<Line<'static>> {
+    wrap(line, max_width)
+        .into_iter()
+        .map(|(line, _)| line)
+        .collect()
+}
+
+/// [`wrap_line`], with where each wrapped line starts in the text of `line`.
+fn wrap(line: &Line<'_>, max_width: usize) -> Vec<(Line<'static>, usize)> {
+    let max_width = max_width.max(1);
+    if line.width() <= max_width {
+        return vec![(owned(line), 0)];
+    }
+
+    let mut span_start = 0;
+    let graphemes: Vec<Grapheme> = line
+        .spans
+        .iter()
+        .flat_map(|span| {
+            let start = span_start;
+            span_start += span.content.len();
+            span.content
+                .grapheme_indices(true)
+                .map(move |(offset, symbol)| Grapheme {
+                    symbol,
+                    style: span.style,
+                    width: symbol.width(),
+                    offset: start + offset,
+                })
+        })
+        .collect();
+
+    // a prefix taking most of the line would leave no room for anything after it.
+    let indent = hanging_indent(line).filter(|indent| *indent <= max_width / 2);
+    let indent = indent.unwrap_or(0);
+
+    let mut lines: Vec<Vec<&Grapheme>> = vec![Vec::new()];
+    let mut width = 0;
+    let mut index = 0;
+    while index < graphemes.len() {
+        // the next word, or run of whitespace.
+        let whitespace = graphemes[index].is_whitespace();
+        let end = graphemes[index..]
+            .iter()
+            .position(|grapheme| grapheme.is_whitespace() != whitespace)
+            .map_or(graphemes.len(), |len| index + len);
+        let word = &graphemes[index..end];
+        let word_width: usize = word.iter().map(|grapheme| grapheme.width).sum();
+        index = end;
+
+        let continued = lines.len() > 1;
+        if whitespace {
+            // whitespace is dropped where a line is broken.
+            if continued && width == indent {
+                continue;
+            }
+            for grapheme in word {
+                if width + grapheme.width > max_width {
+                    break;
+                }
+                lines.last_mut().unwrap().push(grapheme);
+                width += grapheme.width;
+            }
+            continue;
+        }
+
+        let room = max_width - indent;
+        if width + word_width > max_width && word_width <= room {
+            trim_end(lines.last_mut().unwrap());
+            lines.push(Vec::new());
+            width = indent;
+        }
+        for grapheme in word {
+            // a wide grapheme at the start of a line is kept even if it doesn't fit.
+            if width + grapheme.width > max_width && width > indent {
+                trim_end(lines.last_mut().unwrap());
+                lines.push(Vec::new());
+                width = indent;
+            }
+            lines.last_mut().unwrap().push(grapheme);
+            width += grapheme.width;
+        }
+    }
+
+    let mut start = 0;
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(number, graphemes)| {
+            start = graphemes.first().map_or(start, |grapheme| grapheme.offset);
+            let mut spans: Vec<Span<'static>> = Vec::new();
+            for grapheme in graphemes {
+                match spans.last_mut() {
+                    Some(span) if span.style == grapheme.style => {
+                        span.content.to_mut().push_str(grapheme.symbol)
+                    }
+                    _ => spans.push(Span::styled(grapheme.symbol.to_string(), grapheme.style)),
+                }
+            }
+            if number > 0 && indent > 0 {
+                spans.insert(0, Span::raw(" ".repeat(indent)));
+            }
+            (Line::from(spans), start)
+        })
+        .collect()
+}
+
+/// The width of the leading spans up to and including the first one ending in `": "`.
+fn hanging_indent(line: &Line<'_>) -> Option<usize> {
+    let mut width = 0;
+    for span in &line.spans {
+        width += span.width();
+        if span.content.ends_with(": ") {
+            return Some(width);
+        }
+    }
+    None
+}
+
+fn trim_end(line: &mut Vec<&Grapheme>) {
+    while line.last().is_some_and(|grapheme| grapheme.is_whitespace()) {
+        line.pop();
+    }
+}
+
+fn owned(line: &Line<'_>) -> Line<'static> {
+    Line::from(
+        line.spans
+            .iter()
+            .map(|span| Span::styled(span.content.to_string(), span.style))
+            .collect::<Vec<_>>(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cache::Names,
+        filter::parse_rules,
+        store::{Payload, StoredEvent},
+    };
+
+    fn wrap(spans: Vec<&'static str>, max_width: usize) -> Vec<String> {
+        let line = Line::from(spans.into_iter().map(Span::raw).collect::<Vec<_>>());
+        wrap_line(&line, max_width)
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn short_line_is_kept() {
+        assert_eq!(wrap(vec!["user: ", "hi"], 20), ["user: hi"]);
+    }
+
+    #[test]
+    fn breaks_between_words() {
+        assert_eq!(wrap(vec!["one two three"], 8), ["one two", "three"]);
+    }
+
+    #[test]
+    fn wide_characters_are_not_split() {
+        assert_eq!(wrap(vec!["你好世界"], 5), ["你好", "世界"]);
+        assert_eq!(wrap(vec!["ab😀cd"], 3), ["ab", "😀c", "d"]);
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_letter() {
+        assert_eq!(
+            wrap(vec!["e\u{301}e\u{301}e\u{301}e\u{301}"], 2),
+            ["e\u{301}e\u{301}", "e\u{301}e\u{301}"]
+        );
+    }
+
+    #[test]
+    fn long_word_is_broken_anywhere() {
+        assert_eq!(
+            wrap(vec!["a verylongword b"], 5),
+            ["a ver", "ylong", "word", "b"]
+        );
+    }
+
+    #[test]
+    fn continuation_lines_are_indented_after_the_prefix() {
+        assert_eq!(
+            wrap(vec!["user: ", "hello world again"], 12),
+            ["user: hello", "      world", "      again"]
+        );
+    }
+
+    fn unknown_event(variant: &str) -> StoredEvent {
+        let payload = Payload::Unknown {
+            variant: variant.to_string(),
+            data: serde_json::Value::Null,
+            error: None,
+        };
+        StoredEvent::new(payload, Names::default(), 0)
+    }
+
+    #[test]
+    fn update_only_looks_at_new_events() {
+        let config = Config::default();
+        let mut events = EventStore::new(100, None);
+        let mut cache = WrapCache::default();
+        for _ in 0..3 {
+            events.push(unknown_event("Quiet"));
+        }
+
+        // nothing passes, but the events are still seen.
+        let rules = parse_rules("kind:Loud").unwrap();
+        let filter = ActiveFilter::new(String::new(), &rules).unwrap();
+        let changes = cache.update(&events, &config, Some(&filter), 80);
+        assert_eq!((changes.added, cache.total_lines()), (0, 0));
+        assert_eq!(cache.last_seen, Some(2));
+
+        events.push(unknown_event("Loud"));
+        let changes = cache.update(&events, &config, Some(&filter), 80);
+        assert_eq!(changes.added, 1);
+        assert_eq!(cache.last_seen, Some(3));
+        assert_eq!(cache.line_at(0).map(|(seq, _)| seq), Some(3));
+
+        let changes = cache.update(&events, &config, Some(&filter), 80);
+        assert_eq!(changes.added, 0);
+        assert_eq!(cache.line_at(0).map(|(seq, _)| seq), Some(3));
+    }
+
+    #[test]
+    fn search_matches_across_a_break() {
+        let rendered = [
+            Line::from(vec![Span::raw("user: "), Span::raw("hello world again")]),
+            Line::raw("second world"),
+        ];
+        let mut entry = CachedEvent::new(1, &rendered, 12);
+        assert_eq!(entry.lines.len(), 4);
+
+        entry.find_matches(Some(&Regex::new("o w").unwrap()));
+        assert_eq!(entry.matches, [0, 1]);
+        entry.find_matches(Some(&Regex::new("world").unwrap()));
+        assert_eq!(entry.matches, [1, 3]);
+        entry.find_matches(None);
+        assert!(entry.matches.is_empty());
+    }
+
+    #[test]
+    fn wide_prefix_is_not_used_as_indent() {
+        assert_eq!(
+            wrap(vec!["a long name: ", "hi there"], 16),
+            ["a long name: hi", "there"]
+        );
+    }
+}